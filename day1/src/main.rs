@@ -1,99 +1,526 @@
-use std::collections::HashSet;
-use std::io::{self, Read};
-use std::str::Split;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::convert::TryFrom;
+use std::env;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Lines};
+use std::process;
+use std::str::FromStr;
 
-struct Transfromer<'a> {
-    previous: i32,
-    input: &'a str,
-    input_iter: Split<'a, char>,
-    repeat: bool,
+//Integer type frequencies are calculated in. Every operation is checked
+trait Frequency: Copy + Ord + fmt::Display + FromStr {
+    fn zero() -> Self;
+    fn checked_add(self, other: Self) -> Option<Self>;
+    fn checked_sub(self, other: Self) -> Option<Self>;
+    fn checked_mul(self, other: Self) -> Option<Self>;
+    fn checked_div(self, other: Self) -> Option<Self>;
+    fn checked_rem_euclid(self, other: Self) -> Option<Self>;
+    fn from_usize(n: usize) -> Option<Self>;
+    fn to_usize(self) -> Option<usize>;
 }
 
-impl<'a> Iterator for Transfromer<'a> {
-    type Item = i32;
+macro_rules! impl_frequency {
+    ($($t:ty),*) => {$(
+        impl Frequency for $t {
+            fn zero() -> Self {
+                0
+            }
+            fn checked_add(self, other: Self) -> Option<Self> {
+                <$t>::checked_add(self, other)
+            }
+            fn checked_sub(self, other: Self) -> Option<Self> {
+                <$t>::checked_sub(self, other)
+            }
+            fn checked_mul(self, other: Self) -> Option<Self> {
+                <$t>::checked_mul(self, other)
+            }
+            fn checked_div(self, other: Self) -> Option<Self> {
+                <$t>::checked_div(self, other)
+            }
+            fn checked_rem_euclid(self, other: Self) -> Option<Self> {
+                <$t>::checked_rem_euclid(self, other)
+            }
+            fn from_usize(n: usize) -> Option<Self> {
+                <$t>::try_from(n).ok()
+            }
+            fn to_usize(self) -> Option<usize> {
+                usize::try_from(self).ok()
+            }
+        }
+    )*};
+}
 
-    fn next(&mut self) -> Option<Self::Item> {
-        match self.get_line_from_input() {
-            None => None,
-            Some(line) => Some(self.get_next_value(line)),
+impl_frequency!(i32, i64, i128);
+
+#[derive(Debug)]
+enum CalibrationError {
+    Io(io::Error),
+    Blank { line: usize },
+    Malformed { line: usize, text: String },
+    //Frequency after this change doesn't fit into the integer type
+    Overflow { line: usize, text: String },
+    //Same, but for the frequencies of the later passes
+    PassOverflow { pass: Option<usize> },
+}
+
+impl fmt::Display for CalibrationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CalibrationError::Io(e) => write!(f, "Can't read the input: {}", e),
+            CalibrationError::Blank { line } => write!(
+                f,
+                "Line {} is blank (use --skip-blank to ignore blank lines)",
+                line
+            ),
+            CalibrationError::Malformed { line, text } => {
+                write!(f, "Line {}: malformed frequency change \"{}\"", line, text)
+            }
+            CalibrationError::Overflow { line, text } => write!(
+                f,
+                "Line {}: frequency overflows after change \"{}\" (try a wider --width)",
+                line, text
+            ),
+            CalibrationError::PassOverflow { pass: Some(pass) } => write!(
+                f,
+                "Frequency overflows at pass {} (try a wider --width)",
+                pass + 1
+            ),
+            CalibrationError::PassOverflow { pass: None } => write!(
+                f,
+                "Frequency overflows while looking for a repeat (try a wider --width)"
+            ),
         }
     }
 }
 
-impl<'a> Transfromer<'a> {
-    fn get_next_value(&mut self, line: &str) -> i32 {
-        let inc: i32 = line.trim_matches('+').parse().unwrap();
-        self.previous = self.previous + inc;
-        self.previous
-    }
+//Reads frequency changes line by line from anything: stdin, a file, a byte slice
+struct Transfromer<R, T> {
+    previous: T,
+    input_iter: Lines<R>,
+    //Number of the last line read, starting from 1
+    line: usize,
+    skip_blank: bool,
+}
 
-    fn get_line_from_input(&mut self) -> Option<&'a str> {
-        match self.input_iter.next() {
-            None => {
-                if self.repeat {
-                    self.input_iter = self.input.split('\n');
-                    return self.input_iter.next();
+impl<R: BufRead, T: Frequency> Iterator for Transfromer<R, T> {
+    type Item = Result<T, CalibrationError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let line = match self.get_line_from_input()? {
+                Ok(line) => line,
+                Err(e) => return Some(Err(CalibrationError::Io(e))),
+            };
+            if line.trim().is_empty() {
+                if self.skip_blank {
+                    continue;
                 }
-                None
+                return Some(Err(CalibrationError::Blank { line: self.line }));
             }
-            Some(line) => Some(line),
+            return Some(self.get_next_value(&line));
+        }
+    }
+}
+
+impl<R: BufRead, T: Frequency> Transfromer<R, T> {
+    fn get_next_value(&mut self, line: &str) -> Result<T, CalibrationError> {
+        let text = line.trim();
+        let malformed = || CalibrationError::Malformed {
+            line: self.line,
+            text: text.to_string(),
+        };
+        //Sign is optional for positive changes, but there is only one of it
+        let digits = text.strip_prefix('+').unwrap_or(text);
+        if digits.len() < text.len() && digits.starts_with(['+', '-']) {
+            return Err(malformed());
         }
+        let inc: T = digits.parse().map_err(|_| malformed())?;
+        self.previous =
+            self.previous
+                .checked_add(inc)
+                .ok_or_else(|| CalibrationError::Overflow {
+                    line: self.line,
+                    text: text.to_string(),
+                })?;
+        Ok(self.previous)
     }
 
-    fn new(input: &'a str, repeat: bool) -> Transfromer<'a> {
+    fn get_line_from_input(&mut self) -> Option<io::Result<String>> {
+        let line = self.input_iter.next()?;
+        self.line += 1;
+        Some(line)
+    }
+
+    fn new(input: R, skip_blank: bool) -> Transfromer<R, T> {
         Transfromer {
-            previous: 0,
-            input,
-            repeat,
-            input_iter: input.split('\n'),
+            previous: T::zero(),
+            input_iter: input.lines(),
+            line: 0,
+            skip_blank,
         }
     }
 
-    fn exhaust(&mut self) -> i32 {
-        self.last().ok_or("Empty iterator?!").unwrap()
+    //Goes through the changes once. Only the frequencies reached on the way
+    //are kept in memory, not the input itself
+    fn calibrate(&mut self, repeats: usize) -> Result<Report<T>, CalibrationError> {
+        let mut frequencies = vec![];
+        //Every next pass is the same as the first one, shifted by the drift
+        let drift = self.try_fold(T::zero(), |current, next| {
+            frequencies.push(current);
+            next
+        })?;
+        Report::new(&frequencies, drift, repeats)
     }
+}
+
+//Frequency #i of pass #k is frequencies[i] + k * drift
+fn at_pass<T: Frequency>(frequency: T, pass: usize, drift: T) -> Result<T, CalibrationError> {
+    T::from_usize(pass)
+        .and_then(|k| k.checked_mul(drift))
+        .and_then(|shift| frequency.checked_add(shift))
+        .ok_or(CalibrationError::PassOverflow { pass: Some(pass) })
+}
+
+struct Repeat<T> {
+    frequency: T,
+    //Number of changes applied to get there
+    step: usize,
+    //Pass over the input it happened in, starting from 1
+    pass: usize,
+}
+
+struct Report<T> {
+    frequency: T,
+    //Reached before the first repeat, or during the first pass if there's none
+    min: T,
+    max: T,
+    distinct: usize,
+    repeat: Option<Repeat<T>>,
+    //First repeated values, in order of appearance
+    repeats: Vec<T>,
+}
 
-    fn find_repeat(&mut self) -> i32 {
-        let mut hs = HashSet::new();
-        self.filter(|el| {
-            if hs.contains(el) {
-                true
+impl<T: Frequency> Report<T> {
+    //`frequencies` are the ones reached during the first pass, starting with 0
+    fn new(frequencies: &[T], drift: T, repeats: usize) -> Result<Self, CalibrationError> {
+        let n = frequencies.len();
+        let starts = first_repeats(frequencies, drift, repeats.max(1))?;
+        let (min, max, distinct) = match starts.first() {
+            //Extremes of a linear function are at the ends, so we only look at
+            //the first and the last passes
+            Some((step, _)) => {
+                let (last_pass, last_index) = (step / n, step % n);
+                let mut extremes = (frequencies[0], frequencies[0]);
+                for (i, frequency) in frequencies.iter().enumerate() {
+                    let pass = if i <= last_index {
+                        last_pass
+                    } else if last_pass > 0 {
+                        last_pass - 1
+                    } else {
+                        break;
+                    };
+                    let last = at_pass(*frequency, pass, drift)?;
+                    extremes = (
+                        extremes.0.min(*frequency).min(last),
+                        extremes.1.max(*frequency).max(last),
+                    );
+                }
+                //Nothing was reached twice before the first repeat
+                (extremes.0, extremes.1, *step)
+            }
+            //Without a repeat every frequency of the first pass is new, and so is the drift
+            None => {
+                let (min, max) = frequencies
+                    .iter()
+                    .fold((drift, drift), |(min, max), f| (min.min(*f), max.max(*f)));
+                (min, max, n + 1)
+            }
+        };
+        let repeat = match starts.first() {
+            None => None,
+            Some((step, i)) => Some(Repeat {
+                frequency: at_pass(frequencies[*i], step / n, drift)?,
+                step: *step,
+                pass: (step - 1) / n + 1,
+            }),
+        };
+        Ok(Self {
+            frequency: drift,
+            min,
+            max,
+            distinct,
+            repeat,
+            repeats: repeated_values(frequencies, drift, starts, repeats)?,
+        })
+    }
+
+    fn to_json(&self) -> String {
+        let repeat = match &self.repeat {
+            None => "null".to_string(),
+            Some(r) => format!(
+                "{{\"frequency\":{},\"step\":{},\"pass\":{}}}",
+                r.frequency, r.step, r.pass
+            ),
+        };
+        format!(
+            "{{\"frequency\":{},\"min\":{},\"max\":{},\"distinct\":{},\"repeat\":{},\"repeats\":[{}]}}",
+            self.frequency,
+            self.min,
+            self.max,
+            self.distinct,
+            repeat,
+            self.repeats
+                .iter()
+                .map(|f| f.to_string())
+                .collect::<Vec<_>>()
+                .join(",")
+        )
+    }
+}
+
+impl<T: Frequency> fmt::Display for Report<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Current frequency is {}", self.frequency)?;
+        match &self.repeat {
+            Some(r) => writeln!(
+                f,
+                "First repetition is {} at step {} (pass {})",
+                r.frequency, r.step, r.pass
+            )?,
+            None => writeln!(
+                f,
+                "No repeat exists, frequencies never reach the same value twice"
+            )?,
+        }
+        writeln!(f, "Frequencies reached: {}..{}", self.min, self.max)?;
+        writeln!(f, "Distinct frequencies seen: {}", self.distinct)?;
+        write!(
+            f,
+            "First repeated values: {}",
+            self.repeats
+                .iter()
+                .map(|f| f.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )
+    }
+}
+
+//Steps at which frequencies of the first pass start repeating, paired with their
+//indexes. Only the `count` earliest ones, in order. Each frequency keeps repeating in
+//every later pass once it has started
+fn first_repeats<T: Frequency>(
+    frequencies: &[T],
+    drift: T,
+    count: usize,
+) -> Result<Vec<(usize, usize)>, CalibrationError> {
+    let n = frequencies.len();
+    //Only MIN % -1 overflows, and everything is divisible by -1
+    let remainder = |f: T| {
+        if drift == T::zero() {
+            T::zero()
+        } else {
+            f.checked_rem_euclid(drift).unwrap_or_else(T::zero)
+        }
+    };
+    //The first pass frequencies can only meet if they're equal modulo the drift.
+    //Sorting puts them next to each other, with equal values in order of appearance
+    let mut order = (0..n).collect::<Vec<_>>();
+    order.sort_unstable_by_key(|i| (remainder(frequencies[*i]), frequencies[*i], *i));
+
+    //Largest of the earliest steps on top, so it's the one to drop
+    let mut earliest = BinaryHeap::new();
+    let mut record = |i: usize, pass: usize| -> Result<(), CalibrationError> {
+        let step = pass
+            .checked_mul(n)
+            .and_then(|s| s.checked_add(i))
+            .ok_or(CalibrationError::PassOverflow { pass: Some(pass) })?;
+        earliest.push((step, i));
+        if earliest.len() > count {
+            earliest.pop();
+        }
+        Ok(())
+    };
+    let passes = |distance: Option<T>| {
+        distance
+            .and_then(|distance| distance.checked_div(drift))
+            .and_then(|pass| pass.to_usize())
+            .ok_or(CalibrationError::PassOverflow { pass: None })
+    };
+    //First index of the previous run of equal frequencies
+    let mut previous: Option<usize> = None;
+    let mut start = 0;
+    while start < n {
+        let i = order[start];
+        let frequency = frequencies[i];
+        //Later appearances of the same value are repeats already in the first pass
+        let mut end = start + 1;
+        while end < n && frequencies[order[end]] == frequency {
+            record(order[end], 0)?;
+            end += 1;
+        }
+        if drift == T::zero() {
+            //Second pass goes exactly where the first one did
+            record(i, 1)?;
+        } else if let Some(p) =
+            previous.filter(|p| remainder(frequencies[*p]) == remainder(frequency))
+        {
+            //Inside a group each frequency is caught up by its neighbour on the side
+            //the drift comes from
+            if drift > T::zero() {
+                record(p, passes(frequency.checked_sub(frequencies[p]))?)?;
             } else {
-                hs.insert(*el);
-                false
+                record(i, passes(frequencies[p].checked_sub(frequency))?)?;
             }
-        }).next()
-        .ok_or("Empty iterator?!")
-        .unwrap()
+        }
+        previous = Some(i);
+        start = end;
     }
+    Ok(earliest.into_sorted_vec())
 }
 
-fn main() {
-    let mut input = String::new();
-    io::stdin().read_to_string(&mut input).unwrap();
-    println!(
-        "Current frequency is {}",
-        Transfromer::new(&input, false).exhaust()
-    );
-    println!(
-        "First repetition is {}",
-        Transfromer::new(&input, true).find_repeat()
-    );
+//Values of the first `count` repeats, given where the earliest of them start
+fn repeated_values<T: Frequency>(
+    frequencies: &[T],
+    drift: T,
+    starts: Vec<(usize, usize)>,
+    count: usize,
+) -> Result<Vec<T>, CalibrationError> {
+    let n = frequencies.len();
+    let mut next = starts.into_iter().map(Reverse).collect::<BinaryHeap<_>>();
+    let mut result = vec![];
+    while result.len() < count {
+        let (step, i) = match next.pop() {
+            None => break,
+            Some(Reverse(repeat)) => repeat,
+        };
+        result.push(at_pass(frequencies[i], step / n, drift)?);
+        let later = step.checked_add(n).ok_or(CalibrationError::PassOverflow {
+            pass: Some(step / n + 1),
+        })?;
+        next.push(Reverse((later, i)));
+    }
+    Ok(result)
 }
 
-fn find_repeat(input: &str) -> i32 {
-    let mut hs: HashSet<i32> = HashSet::new();
-    hs.insert(0);
-    let mut current = 0;
-    loop {
-        for line in input.split("\n") {
-            let inc: i32 = line.trim_matches('+').parse().unwrap();
-            let new_freq = current + inc;
-            if hs.contains(&new_freq) {
-                return new_freq;
+struct Options {
+    path: Option<String>,
+    json: bool,
+    repeats: usize,
+    skip_blank: bool,
+}
+
+fn run<T: Frequency>(options: &Options) {
+    //Changes are read from the file given as an argument, or from stdin
+    let report: Result<Report<T>, _> = match &options.path {
+        Some(path) => File::open(path)
+            .map_err(CalibrationError::Io)
+            .and_then(|file| {
+                Transfromer::new(BufReader::new(file), options.skip_blank)
+                    .calibrate(options.repeats)
+            }),
+        None => Transfromer::new(io::stdin().lock(), options.skip_blank).calibrate(options.repeats),
+    };
+    match report {
+        Ok(report) if options.json => println!("{}", report.to_json()),
+        Ok(report) => println!("{}", report),
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+    }
+}
+
+fn main() {
+    let mut options = Options {
+        path: None,
+        json: false,
+        repeats: 10,
+        skip_blank: false,
+    };
+    let mut width = 64;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--json" => options.json = true,
+            "--skip-blank" => options.skip_blank = true,
+            "--repeats" => {
+                options.repeats = args
+                    .next()
+                    .expect("--repeats requires a number")
+                    .parse()
+                    .unwrap()
+            }
+            "--width" => {
+                width = args
+                    .next()
+                    .expect("--width requires 32, 64 or 128")
+                    .parse()
+                    .unwrap()
             }
-            hs.insert(new_freq);
-            current = new_freq;
+            _ => options.path = Some(arg),
         }
     }
+    match width {
+        32 => run::<i32>(&options),
+        64 => run::<i64>(&options),
+        128 => run::<i128>(&options),
+        _ => panic!("Unsupported width {}, use 32, 64 or 128", width),
+    }
+}
+
+#[test]
+fn test_first_repeat() {
+    let examples = [
+        ("+1\n-2\n+3\n+1", Some(2)),
+        ("+1\n-1", Some(0)),
+        ("+3\n+3\n+4\n-2\n-4", Some(10)),
+        ("-6\n+3\n+8\n+5\n-6", Some(5)),
+        ("+7\n+7\n-2\n-7\n-4", Some(14)),
+        ("+1\n+1\n+1", None),
+    ];
+    for (input, expected) in examples.iter() {
+        let report: Report<i32> = Transfromer::new(input.as_bytes(), false)
+            .calibrate(0)
+            .unwrap();
+        assert_eq!(report.repeat.map(|r| r.frequency), *expected, "{}", input);
+    }
+}
+
+#[test]
+fn test_report() {
+    //0 3 6 10 8 | 7 10: 10 is first reached again at the 7th step
+    let report: Report<i64> = Transfromer::new("+3\n+3\n+4\n-2\n-4".as_bytes(), false)
+        .calibrate(4)
+        .unwrap();
+    let repeat = report.repeat.unwrap();
+    assert_eq!((repeat.step, repeat.pass), (7, 2));
+    assert_eq!((report.min, report.max, report.distinct), (0, 10, 7));
+    //8 14 of the third pass were reached at steps 4 and 8, 12 of the fourth one at step 9
+    assert_eq!(report.repeats, vec![10, 8, 14, 12]);
+}
+
+#[test]
+fn test_errors() {
+    let calibrate = |input: &str, skip_blank| {
+        Transfromer::<_, i32>::new(input.as_bytes(), skip_blank)
+            .calibrate(0)
+            .map(|report| report.frequency)
+            .map_err(|e| e.to_string())
+    };
+    assert_eq!(calibrate("+1\n-2\n", false), Ok(-1));
+    assert_eq!(calibrate("+1\n\n-2", true), Ok(-1));
+    assert_eq!(
+        calibrate("+1\n\n-2", false),
+        Err("Line 2 is blank (use --skip-blank to ignore blank lines)".to_string())
+    );
+    assert_eq!(
+        calibrate("+1\n+-2", false),
+        Err("Line 2: malformed frequency change \"+-2\"".to_string())
+    );
+    assert_eq!(
+        calibrate("+2147483647\n+1", false),
+        Err("Line 2: frequency overflows after change \"+1\" (try a wider --width)".to_string())
+    );
 }
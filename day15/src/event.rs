@@ -0,0 +1,151 @@
+use std::fmt;
+
+//Unit as it's referred to in the log: its id (order of appearance on the map) and faction
+#[derive(Clone, Debug)]
+pub struct UnitRef {
+    pub id: usize,
+    pub faction: String,
+}
+
+#[derive(Clone, Debug)]
+pub enum Event {
+    Moved {
+        round: i32,
+        unit: UnitRef,
+        from: (usize, usize),
+        to: (usize, usize),
+    },
+    Attacked {
+        round: i32,
+        attacker: UnitRef,
+        target: UnitRef,
+        damage: i32,
+        hp: i32,
+    },
+    Died {
+        round: i32,
+        unit: UnitRef,
+        at: (usize, usize),
+    },
+    RoundFinished {
+        round: i32,
+    },
+}
+
+fn json_string(s: &str) -> String {
+    let mut result = String::from("\"");
+    for ch in s.chars() {
+        match ch {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            ch if ch.is_control() => result.push_str(&format!("\\u{:04x}", ch as u32)),
+            ch => result.push(ch),
+        }
+    }
+    result.push('"');
+    result
+}
+
+impl fmt::Display for UnitRef {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{{\"id\":{},\"faction\":{}}}",
+            self.id,
+            json_string(&self.faction)
+        )
+    }
+}
+
+//One JSON object per event, without the trailing newline
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Event::Moved {
+                round,
+                unit,
+                from,
+                to,
+            } => write!(
+                f,
+                "{{\"event\":\"moved\",\"round\":{},\"unit\":{},\"from\":[{},{}],\"to\":[{},{}]}}",
+                round, unit, from.0, from.1, to.0, to.1
+            ),
+            Event::Attacked {
+                round,
+                attacker,
+                target,
+                damage,
+                hp,
+            } => write!(
+                f,
+                "{{\"event\":\"attacked\",\"round\":{},\"attacker\":{},\"target\":{},\"damage\":{},\"hp\":{}}}",
+                round, attacker, target, damage, hp
+            ),
+            Event::Died { round, unit, at } => write!(
+                f,
+                "{{\"event\":\"died\",\"round\":{},\"unit\":{},\"at\":[{},{}]}}",
+                round, unit, at.0, at.1
+            ),
+            Event::RoundFinished { round } => {
+                write!(f, "{{\"event\":\"round_finished\",\"round\":{}}}", round)
+            }
+        }
+    }
+}
+
+#[test]
+fn test_format() {
+    let elf = UnitRef {
+        id: 1,
+        faction: "Elves".to_string(),
+    };
+    let orc = UnitRef {
+        id: 4,
+        faction: "Or\"c\\s\n".to_string(),
+    };
+    let orc_json = r#"{"id":4,"faction":"Or\"c\\s\u000a"}"#;
+    let cases = [
+        (
+            Event::Moved {
+                round: 1,
+                unit: elf.clone(),
+                from: (2, 3),
+                to: (3, 3),
+            },
+            r#"{"event":"moved","round":1,"unit":{"id":1,"faction":"Elves"},"from":[2,3],"to":[3,3]}"#
+                .to_string(),
+        ),
+        (
+            Event::Attacked {
+                round: 2,
+                attacker: elf.clone(),
+                target: orc.clone(),
+                damage: 3,
+                hp: 197,
+            },
+            format!(
+                r#"{{"event":"attacked","round":2,"attacker":{{"id":1,"faction":"Elves"}},"target":{},"damage":3,"hp":197}}"#,
+                orc_json
+            ),
+        ),
+        (
+            Event::Died {
+                round: 3,
+                unit: orc,
+                at: (5, 1),
+            },
+            format!(
+                r#"{{"event":"died","round":3,"unit":{},"at":[5,1]}}"#,
+                orc_json
+            ),
+        ),
+        (
+            Event::RoundFinished { round: 3 },
+            r#"{"event":"round_finished","round":3}"#.to_string(),
+        ),
+    ];
+    for (event, expected) in cases.iter() {
+        assert_eq!(event.to_string(), *expected);
+    }
+}
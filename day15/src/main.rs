@@ -1,20 +1,66 @@
+mod event;
+mod path;
+mod scenario;
+
+use event::{Event, UnitRef};
+use scenario::Scenario;
 use std::cell::RefCell;
 use std::cmp::Ordering;
-use std::collections::HashSet;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::io::{self, Read, Write};
 
 #[derive(Clone, Debug)]
 struct Game {
     units: Vec<RefCell<Unit>>,
     map: Vec<Vec<Node>>,
     dimensions: (usize, usize),
+    scenario: Scenario,
+    //Stop the battle as soon as a unit of this type dies
+    abort_on_death: Option<UnitType>,
+    //Print the map after every completed round
+    verbose: bool,
+    //Full rounds completed so far
+    rounds: i32,
+    //Everything that happened during the battle, if we're recording it
+    events: Option<RefCell<Vec<Event>>>,
+}
+
+impl fmt::Display for Game {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (y, row) in self.map.iter().enumerate() {
+            let mut hps = vec![];
+            for (x, node) in row.iter().enumerate() {
+                match self.get_unit(x, y) {
+                    Some(u) => {
+                        let u = u.borrow();
+                        let glyph = self.faction(&u.unit_type).glyph;
+                        hps.push(format!("{}({})", glyph, u.hp));
+                        write!(f, "{}", glyph)?
+                    }
+                    None => f.write_str(match node {
+                        Node::Wall => "#",
+                        Node::Open => ".",
+                    })?,
+                }
+            }
+            if !hps.is_empty() {
+                write!(f, "   {}", hps.join(", "))?
+            }
+            f.write_str("\n")?
+        }
+        Ok(())
+    }
 }
 
 impl Game {
-    fn from_string(input: &str) -> Self {
+    fn from_string(input: &str, scenario: Scenario) -> Self {
         let mut units = vec![];
 
         let map = input
-            .split("\n")
+            .lines()
+            .filter(|row| !row.is_empty())
             .enumerate()
             .map(|(y, row)| {
                 row.chars()
@@ -22,15 +68,14 @@ impl Game {
                     .map(|(x, ch)| match ch {
                         '#' => Node::Wall,
                         '.' => Node::Open,
-                        'G' => {
-                            units.push(Unit::rc(x, y, UnitType::Goblin));
-                            Node::Open
-                        }
-                        'E' => {
-                            units.push(Unit::rc(x, y, UnitType::Elf));
-                            Node::Open
-                        }
-                        _ => panic!("Unknwn char! {}", ch),
+                        _ => match scenario.by_glyph(ch) {
+                            Some(i) => {
+                                let id = units.len();
+                                units.push(Unit::rc(id, x, y, UnitType(i), &scenario.factions[i]));
+                                Node::Open
+                            }
+                            None => panic!("Unknwn char! {}", ch),
+                        },
                     })
                     .collect::<Vec<_>>()
             })
@@ -40,9 +85,22 @@ impl Game {
             map,
             units,
             dimensions,
+            scenario,
+            abort_on_death: None,
+            verbose: false,
+            rounds: 0,
+            events: None,
         }
     }
 
+    fn faction(&self, ut: &UnitType) -> &scenario::Faction {
+        &self.scenario.factions[ut.0]
+    }
+
+    fn is_hostile(&self, ut: &UnitType, other: &UnitType) -> bool {
+        self.faction(ut).enemies.contains(&other.0)
+    }
+
     fn get(&self, x: usize, y: usize) -> &Node {
         &self.map[y][x]
     }
@@ -54,111 +112,39 @@ impl Game {
         }
     }
 
-    fn get_unit(&self, x: usize, y: usize) -> Option<RefCell<Unit>> {
-        match self
-            .units
-            .iter()
-            .filter(|u| u.borrow().x == x && u.borrow().y == y)
-            .next()
-        {
-            None => None,
-            Some(u) => {
-                //Ignore the dead!
-                match u.borrow().is_dead() {
-                    true => None,
-                    false => Some(u.clone()),
-                }
-            }
-        }
+    fn get_unit(&self, x: usize, y: usize) -> Option<&RefCell<Unit>> {
+        //Ignore the dead! Their square may be taken by someone else already
+        self.units.iter().find(|u| {
+            let u = u.borrow();
+            u.x == x && u.y == y && !u.is_dead()
+        })
     }
 
-    fn can_visit(&self, x: usize, y: usize) -> bool {
-        self.open(x, y) && self.get_unit(x, y).is_none()
+    //Positions of all the living units a unit of given type is hostile to
+    fn enemies(&self, ut: &UnitType) -> Vec<(usize, usize)> {
+        self.units
+            .iter()
+            .map(|u| u.borrow())
+            .filter(|u| self.is_hostile(ut, &u.unit_type) && !u.is_dead())
+            .map(|u| (u.x, u.y))
+            .collect()
     }
 
-    fn route_to_enemy(&self, x: usize, y: usize, ut: &UnitType) -> Option<Vec<(usize, usize)>> {
-        let mut routes = vec![vec![(x, y)]];
-        let mut visited_before: HashSet<(usize, usize)> = HashSet::new();
-        visited_before.insert((x, y));
-        loop {
-            let mut new_routes = routes
-                .iter()
-                .map(|route| {
-                    self.continue_route(&route)
-                        .iter()
-                        .filter(|new_route| {
-                            let head = new_route[new_route.len()];
-                            //Discard visited before and walls
-                            visited_before.contains(&head) && self.open(head.0, head.1)
-                        })
-                        .map(|v| v.clone())
-                        .collect::<Vec<_>>()
-                })
-                .fold(vec![], |mut all_routes, some_routes| {
-                    all_routes.extend(some_routes.iter().map(|v| v.clone()));
-                    all_routes
-                });
-            let enemy_route = new_routes
-                .iter()
-                .filter(|route| {
-                    let head = route[route.len()];
-                    match self.get_unit(head.0, head.1) {
-                        None => false,
-                        Some(u) => u.borrow().unit_type == *ut,
-                    }
-                })
-                .next();
-            if let Some(r) = enemy_route {
-                return Some(r.clone());
-            }
-            visited_before.extend(new_routes.iter().map(|route| route[route.len()]));
-            new_routes.retain(|route| {
-                let head = route[route.len()];
-                self.get_unit(head.0, head.1).is_none()
-            });
-            if new_routes.len() == 0 {
-                return None;
-            }
-            routes = new_routes
-        }
+    //The weakest adjacent enemy. Ties are broken in reading order
+    fn target(&self, x: usize, y: usize, ut: &UnitType) -> Option<&RefCell<Unit>> {
+        self.around(x, y)
+            .iter()
+            .filter_map(|(x, y)| self.get_unit(*x, *y))
+            .filter(|u| self.is_hostile(ut, &u.borrow().unit_type))
+            .min_by_key(|u| u.borrow().hp)
     }
 
-    fn continue_route(&self, route: &Vec<(usize, usize)>) -> Vec<Vec<(usize, usize)>> {
-        let top = route[route.len()];
-        self.around(top.0, top.1)
-            .iter()
-            .filter(|(x, y)| {
-                if route.len() > 1 {
-                    let prev = route[route.len() - 1];
-                    prev.0 != *x && prev.1 != *y
-                } else {
-                    true
-                }
-            })
-            .map(|(x, y)| {
-                let mut clone = route.clone();
-                clone.push((*x, *y));
-                clone
-            })
-            .collect()
+    fn can_visit(&self, x: usize, y: usize) -> bool {
+        self.open(x, y) && self.get_unit(x, y).is_none()
     }
 
     fn around(&self, x: usize, y: usize) -> Vec<(usize, usize)> {
-        let mut result = vec![];
-        //Order matters here. It's Reading order
-        if y != 0 {
-            result.push((x, y - 1))
-        }
-        if x != 0 {
-            result.push((x - 1, y))
-        }
-        if x != self.dimensions.0 {
-            result.push((x + 1, y))
-        }
-        if y != self.dimensions.1 {
-            result.push((x, y + 1))
-        }
-        result
+        path::around(x, y, self.dimensions)
     }
 
     fn sort_units(&mut self) {
@@ -169,42 +155,222 @@ impl Game {
             });
     }
 
-    fn tick(&mut self) {
-        self.sort_units();
+    fn tick(&mut self) -> Round {
         self.units.retain(|u| !u.borrow().is_dead());
+        self.sort_units();
 
+        //Whether anybody moved or attacked this round
+        let mut active = false;
         for unit in self.units.iter() {
-            let optional_route = self.route_to_enemy(
-                unit.borrow().x,
-                unit.borrow().y,
-                &unit.borrow().enemy_type(),
-            );
-            //immediate proximity!
-            match optional_route {
-                None => {
-                    println!("Got nothing to do!");
+            if unit.borrow().is_dead() {
+                continue;
+            }
+            let unit_type = unit.borrow().unit_type.clone();
+            let enemies = self.enemies(&unit_type);
+            if enemies.is_empty() {
+                //With more than two factions somebody else may still have a fight to finish
+                if self.units.iter().any(|u| {
+                    let u = u.borrow();
+                    !u.is_dead() && !self.enemies(&u.unit_type).is_empty()
+                }) {
+                    continue;
                 }
-                Some(route) => {
-                    let head = route[route.len()];
-                    let tail = route[0];
-                    if route.len() == 2 {
-                        self.attack(unit, &self.get_unit(head.0, head.1).unwrap())
-                    } else {
-                        self.step(unit, tail.0, tail.1)
-                    }
+                return Round::CombatOver;
+            }
+            let (x, y) = (unit.borrow().x, unit.borrow().y);
+            if self.target(x, y, &unit_type).is_none() {
+                if let Some((nx, ny)) =
+                    path::next_step((x, y), &enemies, self.dimensions, |x, y| {
+                        self.can_visit(x, y)
+                    })
+                {
+                    self.step(unit, nx, ny);
+                    active = true;
+                }
+            }
+            let (x, y) = (unit.borrow().x, unit.borrow().y);
+            if let Some(target) = self.target(x, y, &unit_type) {
+                self.attack(unit, target);
+                active = true;
+                let target = target.borrow();
+                if target.is_dead() && self.abort_on_death.as_ref() == Some(&target.unit_type) {
+                    return Round::Aborted(target.clone());
                 }
             }
         }
+        if active {
+            Round::Completed
+        } else {
+            Round::Stalemate
+        }
+    }
+
+    fn set_attack(&mut self, ut: &UnitType, attack: i32) {
+        self.units
+            .iter()
+            .filter(|u| u.borrow().unit_type == *ut)
+            .for_each(|u| u.borrow_mut().attack = attack)
+    }
+
+    fn unit_ref(&self, unit: &Unit) -> UnitRef {
+        UnitRef {
+            id: unit.id,
+            faction: self.faction(&unit.unit_type).name.clone(),
+        }
+    }
+
+    fn emit(&self, event: Event) {
+        if let Some(events) = &self.events {
+            events.borrow_mut().push(event)
+        }
     }
 
     fn step(&self, unit: &RefCell<Unit>, x: usize, y: usize) {
         let mut borrowed = unit.borrow_mut();
+        self.emit(Event::Moved {
+            round: self.rounds + 1,
+            unit: self.unit_ref(&borrowed),
+            from: (borrowed.x, borrowed.y),
+            to: (x, y),
+        });
         borrowed.x = x;
         borrowed.y = y;
     }
 
     fn attack(&self, attacker: &RefCell<Unit>, target: &RefCell<Unit>) {
-        target.borrow_mut().hp = attacker.borrow().attack
+        let attacker = attacker.borrow();
+        let mut target = target.borrow_mut();
+        target.hp -= attacker.attack;
+        self.emit(Event::Attacked {
+            round: self.rounds + 1,
+            attacker: self.unit_ref(&attacker),
+            target: self.unit_ref(&target),
+            damage: attacker.attack,
+            hp: target.hp,
+        });
+        if target.is_dead() {
+            self.emit(Event::Died {
+                round: self.rounds + 1,
+                unit: self.unit_ref(&target),
+                at: (target.x, target.y),
+            })
+        }
+    }
+
+    fn fight(&mut self) -> Result<Outcome, Casualty> {
+        if self.verbose {
+            println!("Initially:\n{}", self);
+        }
+        let mut stalemate = false;
+        loop {
+            match self.tick() {
+                Round::Completed => {
+                    self.rounds += 1;
+                    self.emit(Event::RoundFinished { round: self.rounds });
+                    if self.verbose {
+                        match self.rounds {
+                            1 => println!("After 1 round:\n{}", self),
+                            _ => println!("After {} rounds:\n{}", self.rounds, self),
+                        }
+                    }
+                }
+                Round::CombatOver => break,
+                Round::Stalemate => {
+                    stalemate = true;
+                    break;
+                }
+                Round::Aborted(unit) => {
+                    return Err(Casualty {
+                        round: self.rounds + 1,
+                        faction: self.faction(&unit.unit_type).name.clone(),
+                        unit,
+                    })
+                }
+            }
+        }
+        let survivors = self
+            .units
+            .iter()
+            .map(|u| u.borrow())
+            .filter(|u| !u.is_dead())
+            .collect::<Vec<_>>();
+        Ok(Outcome {
+            stalemate,
+            rounds: self.rounds,
+            hp: survivors.iter().map(|u| u.hp).sum(),
+            winners: self
+                .scenario
+                .factions
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| survivors.iter().any(|u| u.unit_type.0 == *i))
+                .map(|(_, faction)| faction.name.clone())
+                .collect(),
+        })
+    }
+}
+
+enum Round {
+    Completed,
+    //Somebody found no targets before the round was finished
+    CombatOver,
+    //Nobody could move or attack, so nothing will ever change
+    Stalemate,
+    //A unit of the `abort_on_death` type died
+    Aborted(Unit),
+}
+
+#[derive(Debug)]
+struct Casualty {
+    round: i32,
+    faction: String,
+    unit: Unit,
+}
+
+impl fmt::Display for Casualty {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} unit at {},{} died in round {}",
+            self.faction, self.unit.x, self.unit.y, self.round
+        )
+    }
+}
+
+struct Outcome {
+    //Enemies are left, but they can't reach each other
+    stalemate: bool,
+    rounds: i32,
+    hp: i32,
+    winners: Vec<String>,
+}
+
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.stalemate {
+            writeln!(f, "Combat stalls after {} full rounds", self.rounds)?;
+            writeln!(
+                f,
+                "{} survive with {} total hit points left",
+                self.winners.join(" and "),
+                self.hp
+            )?;
+        } else {
+            writeln!(f, "Combat ends after {} full rounds", self.rounds)?;
+            writeln!(
+                f,
+                "{} win with {} total hit points left",
+                self.winners.join(" and "),
+                self.hp
+            )?;
+        }
+        write!(
+            f,
+            "Outcome: {} * {} = {}",
+            self.rounds,
+            self.hp,
+            self.rounds * self.hp
+        )
     }
 }
 
@@ -216,42 +382,203 @@ enum Node {
 
 #[derive(Clone, Debug)]
 struct Unit {
+    id: usize,
     x: usize,
     y: usize,
-    hp: u8,
-    attack: u8,
+    hp: i32,
+    attack: i32,
     unit_type: UnitType,
 }
 
 impl Unit {
-    fn rc(x: usize, y: usize, ut: UnitType) -> RefCell<Self> {
+    fn rc(
+        id: usize,
+        x: usize,
+        y: usize,
+        ut: UnitType,
+        faction: &scenario::Faction,
+    ) -> RefCell<Self> {
         RefCell::new(Self {
+            id,
             x,
             y,
             unit_type: ut,
-            hp: 200,
-            attack: 3,
+            hp: faction.hp,
+            attack: faction.attack,
         })
     }
 
     fn is_dead(&self) -> bool {
         self.hp <= 0
     }
+}
 
-    fn enemy_type(&self) -> UnitType {
-        match self.unit_type {
-            UnitType::Elf => UnitType::Goblin,
-            UnitType::Goblin => UnitType::Elf,
+//Index of the unit's faction in the scenario
+#[derive(Clone, Debug, PartialEq)]
+struct UnitType(usize);
+
+//Lowest attack power that wins the battle for the faction without a single loss.
+//None if even killing every enemy with a single hit doesn't help, or they can't be reached
+fn find_attack(game: &Game, ut: &UnitType) -> Option<(i32, Outcome)> {
+    let faction = game.faction(ut);
+    let max_hp = faction
+        .enemies
+        .iter()
+        .map(|enemy| game.scenario.factions[*enemy].hp)
+        .max()
+        .unwrap_or(0);
+    let mut attack = faction.attack + 1;
+    loop {
+        let mut attempt = game.clone();
+        attempt.abort_on_death = Some(ut.clone());
+        attempt.set_attack(ut, attack);
+        match attempt.fight() {
+            Ok(ref outcome) if outcome.stalemate => {
+                println!("{} attack {}: stalemate", faction.name, attack)
+            }
+            Ok(outcome) => {
+                println!("{} attack {}: no casualties", faction.name, attack);
+                return Some((attack, outcome));
+            }
+            Err(casualty) => println!("{} attack {}: {}", faction.name, attack, casualty),
         }
+        if attack >= max_hp {
+            return None;
+        }
+        attack += 1;
     }
 }
 
-#[derive(Clone, Debug, PartialEq)]
-enum UnitType {
-    Elf,
-    Goblin,
+fn main() {
+    let mut verbose = false;
+    let mut scenario = Scenario::default();
+    let mut protect = "Elves".to_string();
+    let mut events = None;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-v" | "--verbose" => verbose = true,
+            "--scenario" => {
+                let path = args.next().expect("--scenario requires a path");
+                scenario = fs::read_to_string(&path)
+                    .unwrap()
+                    .parse()
+                    .unwrap_or_else(|e| panic!("Invalid scenario {}: {}", path, e))
+            }
+            "--events" => events = Some(args.next().expect("--events requires a path")),
+            "--protect" => protect = args.next().expect("--protect requires a faction name"),
+            _ => panic!("Unknown argument {}", arg),
+        }
+    }
+
+    let mut input = String::new();
+    io::stdin().read_to_string(&mut input).unwrap();
+    let game = Game::from_string(&input, scenario);
+    let mut part1 = game.clone();
+    part1.verbose = verbose;
+    if events.is_some() {
+        part1.events = Some(RefCell::new(vec![]));
+    }
+    println!("{}", part1.fight().unwrap());
+    if let (Some(path), Some(log)) = (events, part1.events) {
+        let mut file = io::BufWriter::new(fs::File::create(path).unwrap());
+        for event in log.into_inner() {
+            writeln!(file, "{}", event).unwrap();
+        }
+    }
+
+    match game.scenario.find(&protect) {
+        None => println!("No faction named {}, skipping attack search", protect),
+        Some(i) => match find_attack(&game, &UnitType(i)) {
+            Some((attack, outcome)) => {
+                println!(
+                    "{} need {} attack power to win without losses",
+                    protect, attack
+                );
+                println!("{}", outcome);
+            }
+            None => println!("No attack power lets {} win without losses", protect),
+        },
+    }
 }
 
-fn main() {
-    println!("Hello, world!");
+#[test]
+fn test_stalemate() {
+    let mut game = Game::from_string("#####\n#E#G#\n#####", Scenario::default());
+    let outcome = game.fight().unwrap();
+    assert!(outcome.stalemate);
+    assert_eq!((outcome.rounds, outcome.hp), (0, 400));
+}
+
+#[cfg(test)]
+const EXAMPLES: [&str; 6] = [
+    "#######\n#.G...#\n#...EG#\n#.#.#G#\n#..G#E#\n#.....#\n#######",
+    "#######\n#G..#E#\n#E#E.E#\n#G.##.#\n#...#E#\n#...E.#\n#######",
+    "#######\n#E..EG#\n#.#G.E#\n#E.##E#\n#G..#.#\n#..E#.#\n#######",
+    "#######\n#E.G#.#\n#.#G..#\n#G.#.G#\n#G..#.#\n#...E.#\n#######",
+    "#######\n#.E...#\n#.#..G#\n#.###.#\n#E#G#G#\n#...#G#\n#######",
+    "#########\n#G......#\n#.E.#...#\n#..##..G#\n#...##..#\n#...#...#\n#.G...G.#\n#.....G.#\n#########",
+];
+
+#[test]
+fn test_combat() {
+    let expected = [
+        (47, 590, 27730, "Goblins"),
+        (37, 982, 36334, "Elves"),
+        (46, 859, 39514, "Elves"),
+        (35, 793, 27755, "Goblins"),
+        (54, 536, 28944, "Goblins"),
+        (20, 937, 18740, "Goblins"),
+    ];
+    for (map, (rounds, hp, outcome, winner)) in EXAMPLES.iter().zip(expected.iter()) {
+        let result = Game::from_string(map, Scenario::default()).fight().unwrap();
+        assert!(!result.stalemate);
+        assert_eq!((result.rounds, result.hp), (*rounds, *hp), "{}", map);
+        assert_eq!(result.rounds * result.hp, *outcome);
+        assert_eq!(result.winners, vec![winner.to_string()]);
+    }
+
+    //Lowest attack power letting the Elves win without losses, the second example is skipped
+    let expected = [
+        (0, 15, 29, 172),
+        (2, 4, 33, 948),
+        (3, 15, 37, 94),
+        (4, 12, 39, 166),
+        (5, 34, 30, 38),
+    ];
+    for (i, attack, rounds, hp) in expected.iter() {
+        let game = Game::from_string(EXAMPLES[*i], Scenario::default());
+        let (found, outcome) = find_attack(&game, &UnitType(0)).unwrap();
+        assert_eq!(
+            (found, outcome.rounds, outcome.hp),
+            (*attack, *rounds, *hp),
+            "{}",
+            EXAMPLES[*i]
+        );
+    }
+
+    //The goblin strikes first and every elf dies from a single hit,
+    //so the search gives up once the elves kill with a single hit too
+    let scenario = "[Elves]\nglyph = E\nhp = 1\n\n[Goblins]\nglyph = G\n"
+        .parse()
+        .unwrap();
+    let game = Game::from_string("####\n#GE#\n####", scenario);
+    assert!(find_attack(&game, &UnitType(0)).is_none());
+}
+
+#[test]
+fn test_display() {
+    let mut game = Game::from_string(EXAMPLES[0], Scenario::default());
+    game.tick();
+    assert_eq!(
+        format!("{}", game),
+        "#######
+#..G..#   G(200)
+#...EG#   E(197), G(197)
+#.#G#G#   G(200), G(197)
+#...#E#   E(197)
+#.....#
+#######
+"
+    );
 }
@@ -0,0 +1,254 @@
+use std::collections::VecDeque;
+
+//Neighbouring squares. Order matters here. It's Reading order
+pub fn around(x: usize, y: usize, dimensions: (usize, usize)) -> Vec<(usize, usize)> {
+    let mut result = vec![];
+    if y != 0 {
+        result.push((x, y - 1))
+    }
+    if x != 0 {
+        result.push((x - 1, y))
+    }
+    if x + 1 < dimensions.0 {
+        result.push((x + 1, y))
+    }
+    if y + 1 < dimensions.1 {
+        result.push((x, y + 1))
+    }
+    result
+}
+
+//Number of steps from the start square to every square reachable from it
+pub struct DistanceField {
+    distances: Vec<Vec<Option<usize>>>,
+}
+
+impl DistanceField {
+    //The start square itself doesn't have to be passable, it's usually occupied by the unit
+    pub fn new<F>(start: (usize, usize), dimensions: (usize, usize), passable: F) -> Self
+    where
+        F: Fn(usize, usize) -> bool,
+    {
+        let mut distances = vec![vec![None; dimensions.0]; dimensions.1];
+        distances[start.1][start.0] = Some(0);
+        let mut queue = VecDeque::new();
+        queue.push_back((start, 0));
+        while let Some(((x, y), distance)) = queue.pop_front() {
+            for (nx, ny) in around(x, y, dimensions) {
+                if distances[ny][nx].is_none() && passable(nx, ny) {
+                    distances[ny][nx] = Some(distance + 1);
+                    queue.push_back(((nx, ny), distance + 1));
+                }
+            }
+        }
+        Self { distances }
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Option<usize> {
+        self.distances[y][x]
+    }
+}
+
+//Open squares adjacent to any of the targets, in reading order
+pub fn in_range<F>(
+    targets: &[(usize, usize)],
+    dimensions: (usize, usize),
+    passable: F,
+) -> Vec<(usize, usize)>
+where
+    F: Fn(usize, usize) -> bool,
+{
+    let mut result = targets
+        .iter()
+        .flat_map(|(x, y)| around(*x, *y, dimensions))
+        .filter(|(x, y)| passable(*x, *y))
+        .collect::<Vec<_>>();
+    result.sort_by_key(|(x, y)| (*y, *x));
+    result.dedup();
+    result
+}
+
+//Reachable square requiring the fewest steps. Ties are broken in reading order
+pub fn nearest(field: &DistanceField, squares: &[(usize, usize)]) -> Option<(usize, usize)> {
+    squares
+        .iter()
+        .filter_map(|(x, y)| field.get(*x, *y).map(|distance| (distance, *y, *x)))
+        .min()
+        .map(|(_, y, x)| (x, y))
+}
+
+//Step from `from` that brings us closer to `to`. Ties are broken in reading order
+pub fn first_step<F>(
+    from: (usize, usize),
+    to: (usize, usize),
+    dimensions: (usize, usize),
+    passable: F,
+) -> Option<(usize, usize)>
+where
+    F: Fn(usize, usize) -> bool,
+{
+    let field = DistanceField::new(to, dimensions, passable);
+    around(from.0, from.1, dimensions)
+        .into_iter()
+        .filter_map(|(x, y)| field.get(x, y).map(|distance| (distance, (x, y))))
+        //min_by_key returns the first of equal elements, and `around` is in reading order
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, square)| square)
+}
+
+//Where a unit standing at `from` should step to get in range of one of the `targets`.
+//None if it can't reach any of them
+pub fn next_step<F>(
+    from: (usize, usize),
+    targets: &[(usize, usize)],
+    dimensions: (usize, usize),
+    passable: F,
+) -> Option<(usize, usize)>
+where
+    F: Fn(usize, usize) -> bool,
+{
+    let field = DistanceField::new(from, dimensions, &passable);
+    let squares = in_range(targets, dimensions, &passable);
+    let chosen = nearest(&field, &squares)?;
+    first_step(from, chosen, dimensions, &passable)
+}
+
+#[cfg(test)]
+struct TestMap {
+    open: Vec<Vec<bool>>,
+    elf: (usize, usize),
+    goblins: Vec<(usize, usize)>,
+    dimensions: (usize, usize),
+}
+
+#[cfg(test)]
+impl TestMap {
+    fn new(input: &str) -> Self {
+        let mut elf = (0, 0);
+        let mut goblins = vec![];
+        let open = input
+            .lines()
+            .enumerate()
+            .map(|(y, row)| {
+                row.chars()
+                    .enumerate()
+                    .map(|(x, ch)| match ch {
+                        'E' => {
+                            elf = (x, y);
+                            false
+                        }
+                        'G' => {
+                            goblins.push((x, y));
+                            false
+                        }
+                        ch => ch != '#',
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>();
+        let dimensions = (open[0].len(), open.len());
+        Self {
+            open,
+            elf,
+            goblins,
+            dimensions,
+        }
+    }
+
+    fn passable(&self) -> impl Fn(usize, usize) -> bool + '_ {
+        move |x, y| self.open[y][x]
+    }
+
+    //Squares of the test map marked with `mark`, in reading order
+    fn marked(input: &str, mark: char) -> Vec<(usize, usize)> {
+        input
+            .lines()
+            .enumerate()
+            .flat_map(|(y, row)| {
+                row.chars()
+                    .enumerate()
+                    .filter(move |(_, ch)| *ch == mark)
+                    .map(move |(x, _)| (x, y))
+            })
+            .collect()
+    }
+}
+
+#[test]
+fn test_targets_to_chosen() {
+    let map = TestMap::new("#######\n#E..G.#\n#...#.#\n#.G.#G#\n#######");
+    let field = DistanceField::new(map.elf, map.dimensions, map.passable());
+
+    let squares = in_range(&map.goblins, map.dimensions, map.passable());
+    assert_eq!(
+        squares,
+        TestMap::marked("#######\n#E.?G?#\n#.?.#?#\n#?G?#G#\n#######", '?')
+    );
+
+    let reachable = squares
+        .iter()
+        .filter(|(x, y)| field.get(*x, *y).is_some())
+        .cloned()
+        .collect::<Vec<_>>();
+    assert_eq!(
+        reachable,
+        TestMap::marked("#######\n#E.@G.#\n#.@.#.#\n#@G@#G#\n#######", '@')
+    );
+
+    let min_distance = reachable
+        .iter()
+        .filter_map(|(x, y)| field.get(*x, *y))
+        .min()
+        .unwrap();
+    let nearest_squares = reachable
+        .iter()
+        .filter(|(x, y)| field.get(*x, *y) == Some(min_distance))
+        .cloned()
+        .collect::<Vec<_>>();
+    assert_eq!(
+        nearest_squares,
+        TestMap::marked("#######\n#E.!G.#\n#.!.#.#\n#!G.#G#\n#######", '!')
+    );
+
+    assert_eq!(nearest(&field, &squares), Some((3, 1)));
+}
+
+#[test]
+fn test_step_in_reading_order() {
+    let map = TestMap::new("#######\n#.E...#\n#.....#\n#...G.#\n#######");
+    let field = DistanceField::new(map.elf, map.dimensions, map.passable());
+    let squares = in_range(&map.goblins, map.dimensions, map.passable());
+    assert_eq!(
+        squares,
+        TestMap::marked("#######\n#.E...#\n#...?.#\n#..?G?#\n#######", '?')
+    );
+    let chosen = nearest(&field, &squares);
+    assert_eq!(chosen, Some((4, 2)));
+
+    //Distance from the chosen square, as in the "Distance" column of the puzzle
+    let distances = DistanceField::new((4, 2), map.dimensions, map.passable());
+    let expected = ["#4E212#", "#32101#", "#432G2#"];
+    for (y, row) in expected.iter().enumerate() {
+        for (x, ch) in row
+            .chars()
+            .enumerate()
+            .filter(|(_, ch)| ch.is_ascii_digit())
+        {
+            assert_eq!(distances.get(x, y + 1), ch.to_digit(10).map(|d| d as usize));
+        }
+    }
+
+    assert_eq!(
+        next_step(map.elf, &map.goblins, map.dimensions, map.passable()),
+        Some((3, 1))
+    );
+}
+
+#[test]
+fn test_unreachable() {
+    let map = TestMap::new("#######\n#E.#G.#\n#..#..#\n#######");
+    assert_eq!(
+        next_step(map.elf, &map.goblins, map.dimensions, map.passable()),
+        None
+    );
+}
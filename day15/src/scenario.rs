@@ -0,0 +1,235 @@
+use std::collections::HashSet;
+use std::str::FromStr;
+
+//Puzzle rules: Elves and Goblins, 200 HP and 3 attack power each
+const DEFAULT: &str = "
+[Elves]
+glyph = E
+hp = 200
+attack = 3
+enemies = Goblins
+
+[Goblins]
+glyph = G
+hp = 200
+attack = 3
+enemies = Elves
+";
+
+#[derive(Clone, Debug)]
+pub struct Faction {
+    pub name: String,
+    pub glyph: char,
+    pub hp: i32,
+    pub attack: i32,
+    //Indices of the factions this one attacks
+    pub enemies: Vec<usize>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Scenario {
+    pub factions: Vec<Faction>,
+}
+
+impl Default for Scenario {
+    fn default() -> Self {
+        DEFAULT.parse().unwrap()
+    }
+}
+
+impl Scenario {
+    pub fn find(&self, name: &str) -> Option<usize> {
+        self.factions.iter().position(|f| f.name == name)
+    }
+
+    pub fn by_glyph(&self, glyph: char) -> Option<usize> {
+        self.factions.iter().position(|f| f.glyph == glyph)
+    }
+}
+
+//Faction as it's written in the file, before enemy names are resolved
+struct Section {
+    name: String,
+    glyph: Option<char>,
+    hp: i32,
+    attack: i32,
+    enemies: Option<Vec<String>>,
+}
+
+impl FromStr for Scenario {
+    type Err = String;
+
+    //Sections of `key = value` lines, one per faction:
+    //  [Goblins]
+    //  glyph = G
+    //  hp = 300        (200 if omitted)
+    //  attack = 5      (3 if omitted)
+    //  enemies = Elves, Dwarves   (every other faction if omitted)
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let mut sections: Vec<Section> = vec![];
+        for (i, line) in input.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let error = |msg: &str| format!("Line {}: {} in \"{}\"", i + 1, msg, line);
+            if line.starts_with('[') && line.ends_with(']') {
+                let name = line[1..line.len() - 1].trim().to_string();
+                if sections.iter().any(|s| s.name == name) {
+                    return Err(error("Duplicate faction"));
+                }
+                sections.push(Section {
+                    name,
+                    glyph: None,
+                    hp: 200,
+                    attack: 3,
+                    enemies: None,
+                });
+                continue;
+            }
+            let section = sections
+                .last_mut()
+                .ok_or_else(|| error("Expected a [faction] header"))?;
+            let chunks = line.splitn(2, '=').map(|c| c.trim()).collect::<Vec<_>>();
+            if chunks.len() != 2 {
+                return Err(error("Expected key = value"));
+            }
+            match chunks[0] {
+                "glyph" => {
+                    let mut chars = chunks[1].chars();
+                    match (chars.next(), chars.next()) {
+                        (Some(ch), None) if ch != '#' && ch != '.' => section.glyph = Some(ch),
+                        _ => {
+                            return Err(error(
+                                "Glyph must be a single character other than # and .",
+                            ))
+                        }
+                    }
+                }
+                "hp" => {
+                    section.hp = match chunks[1].parse() {
+                        Ok(hp) if hp > 0 => hp,
+                        _ => return Err(error("HP must be a positive number")),
+                    }
+                }
+                "attack" => {
+                    section.attack = match chunks[1].parse() {
+                        Ok(attack) if attack > 0 => attack,
+                        _ => return Err(error("Attack must be a positive number")),
+                    }
+                }
+                "enemies" => {
+                    section.enemies = Some(
+                        chunks[1]
+                            .split(',')
+                            .map(|name| name.trim().to_string())
+                            .filter(|name| !name.is_empty())
+                            .collect(),
+                    )
+                }
+                _ => return Err(error("Unknown key")),
+            }
+        }
+
+        let names = sections.iter().map(|s| s.name.clone()).collect::<Vec<_>>();
+        let mut glyphs = HashSet::new();
+        let mut factions = vec![];
+        for (i, section) in sections.into_iter().enumerate() {
+            let glyph = section
+                .glyph
+                .ok_or_else(|| format!("Faction {} has no glyph", section.name))?;
+            if !glyphs.insert(glyph) {
+                return Err(format!("Glyph {} is used by more than one faction", glyph));
+            }
+            let enemies = match &section.enemies {
+                None => (0..names.len()).filter(|j| *j != i).collect(),
+                Some(enemies) => enemies
+                    .iter()
+                    .map(|enemy| {
+                        names.iter().position(|n| n == enemy).ok_or_else(|| {
+                            format!("Unknown enemy {} of faction {}", enemy, section.name)
+                        })
+                    })
+                    .collect::<Result<Vec<_>, _>>()?,
+            };
+            factions.push(Faction {
+                name: section.name,
+                glyph,
+                hp: section.hp,
+                attack: section.attack,
+                enemies,
+            });
+        }
+        if factions.is_empty() {
+            return Err("No factions declared".to_string());
+        }
+        Ok(Self { factions })
+    }
+}
+
+#[test]
+fn test_errors() {
+    let cases = [
+        (
+            "glyph = E",
+            "Line 1: Expected a [faction] header in \"glyph = E\"",
+        ),
+        (
+            "[A]\nglyph = A\n[A]",
+            "Line 3: Duplicate faction in \"[A]\"",
+        ),
+        ("[A]\nglyph", "Line 2: Expected key = value in \"glyph\""),
+        (
+            "[A]\nglyph = #",
+            "Line 2: Glyph must be a single character other than # and . in \"glyph = #\"",
+        ),
+        (
+            "[A]\nglyph = AB",
+            "Line 2: Glyph must be a single character other than # and . in \"glyph = AB\"",
+        ),
+        (
+            "[A]\nhp = 0",
+            "Line 2: HP must be a positive number in \"hp = 0\"",
+        ),
+        (
+            "[A]\nattack = -1",
+            "Line 2: Attack must be a positive number in \"attack = -1\"",
+        ),
+        (
+            "[A]\nattack = x",
+            "Line 2: Attack must be a positive number in \"attack = x\"",
+        ),
+        ("[A]\nspeed = 3", "Line 2: Unknown key in \"speed = 3\""),
+        ("[A]\nhp = 3", "Faction A has no glyph"),
+        (
+            "[A]\nglyph = E\n[B]\nglyph = E",
+            "Glyph E is used by more than one faction",
+        ),
+        (
+            "[A]\nglyph = A\nenemies = B",
+            "Unknown enemy B of faction A",
+        ),
+        ("# nothing here", "No factions declared"),
+    ];
+    for (input, error) in cases.iter() {
+        assert_eq!(input.parse::<Scenario>().err().as_deref(), Some(*error));
+    }
+}
+
+#[test]
+fn test_three_way() {
+    let scenario: Scenario = include_str!("../three_way.scenario").parse().unwrap();
+    let factions = scenario
+        .factions
+        .iter()
+        .map(|f| (f.name.as_str(), f.glyph, f.hp, f.attack, f.enemies.clone()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        factions,
+        vec![
+            ("Elves", 'E', 200, 3, vec![1, 2]),
+            ("Goblins", 'G', 250, 4, vec![0, 2]),
+            ("Dwarves", 'D', 300, 2, vec![0, 1]),
+        ]
+    );
+}
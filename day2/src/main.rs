@@ -1,62 +1,329 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::env;
 use std::io::{self, Read};
+use std::ops::Range;
 use std::str::Chars;
 
 fn main() {
     let mut input = String::new();
     io::stdin().read_to_string(&mut input).unwrap();
-    let part1_res =
-        input
-            .split("\n")
-            .map(|line| counts(line.chars()))
-            .fold((0, 0), |acc, counter| {
-                let counts = counter.iter().fold(HashMap::new(), |mut acc, (_, count)| {
-                    acc.entry(count).or_insert(1);
-                    acc
-                });
-                (
-                    acc.0 + match counts.get(&2) {
-                        None => 0,
-                        Some(_) => 1,
-                    },
-                    acc.1 + match counts.get(&3) {
-                        None => 0,
-                        Some(_) => 1,
-                    },
-                )
-            });
-    println!("Checksum is {}", part1_res.0 * part1_res.1);
-    println!(
-        "Similar part is {}",
-        find_similar(
-            input
-                .split("\n")
-                .map(|line| line.chars().collect())
-                .collect()
-        )
-    );
+    let mut multiplicities = vec![2, 3];
+    let mut subset = None;
+    //Maximum number of differing positions, 1 for the puzzle
+    let mut distance = None;
+    let mut edit_distance = false;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_ref() {
+            "--multiplicities" => {
+                multiplicities = parse_list(&args.next().expect("Multiplicities expected"))
+            }
+            "--checksum" => {
+                subset = Some(parse_list(&args.next().expect("Multiplicities expected")))
+            }
+            "--levenshtein" => edit_distance = true,
+            k => distance = Some(k.parse().expect("Distance should be a number")),
+        }
+    }
+    let tallies = histogram(input.lines(), &multiplicities);
+    for (multiplicity, tally) in tallies.tallies.iter() {
+        println!(
+            "{} IDs have a letter appearing exactly {} times",
+            tally, multiplicity
+        );
+    }
+    match tallies.checksum(subset.as_ref().unwrap_or(&multiplicities)) {
+        Some(checksum) => println!("Checksum is {}", checksum),
+        None => println!("Checksum can only use the counted multiplicities"),
+    }
+    let ids = input
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(|line| line.chars().collect())
+        .collect::<Vec<Vec<char>>>();
+    if edit_distance {
+        let pairs = find_closest(&ids, distance);
+        for pair in pairs.iter() {
+            println!(
+                "{} {} are {} edits apart, common part is {}",
+                ids[pair.first].iter().collect::<String>(),
+                ids[pair.second].iter().collect::<String>(),
+                pair.distance,
+                pair.common
+            );
+        }
+        if pairs.is_empty() {
+            println!("No IDs are close enough");
+        }
+        return;
+    }
+    let pairs = find_similar(&ids, distance.unwrap_or(1));
+    for pair in pairs.iter() {
+        println!(
+            "{} {} differ at {:?}",
+            ids[pair.first].iter().collect::<String>(),
+            ids[pair.second].iter().collect::<String>(),
+            pair.positions
+        );
+    }
+    match pairs.iter().find(|pair| pair.positions.len() == 1) {
+        Some(pair) => println!("Similar part is {}", pair.common(&ids)),
+        None => println!("No IDs differ by exactly one character"),
+    }
+}
+
+fn parse_list(s: &str) -> Vec<usize> {
+    s.split(',')
+        .map(|m| m.trim().parse().expect("Multiplicity should be a number"))
+        .collect()
 }
 
-fn counts(i: Chars) -> HashMap<char, i32> {
+fn counts(i: Chars) -> HashMap<char, usize> {
     i.fold(HashMap::new(), |mut acc, ch| {
         *acc.entry(ch).or_insert(0) += 1;
         acc
     })
 }
 
-fn find_similar(input: Vec<Vec<char>>) -> String {
-    for line in &input {
-        for similar in &input {
-            let matching: Vec<_> = line
-                .iter()
-                .enumerate()
-                .filter(|(i, ch)| **ch == similar[*i])
-                .map(|(_, ch)| *ch)
-                .collect();
-            if matching.len() == line.len() - 1 {
-                return matching.iter().collect();
+//Number of IDs having some letter exactly N times, for every requested N
+struct Histogram {
+    tallies: BTreeMap<usize, usize>,
+}
+
+impl Histogram {
+    //Product of the tallies of the chosen multiplicities.
+    //None if one of them wasn't counted
+    fn checksum(&self, multiplicities: &[usize]) -> Option<usize> {
+        multiplicities
+            .iter()
+            .map(|m| self.tallies.get(m))
+            .product::<Option<usize>>()
+    }
+}
+
+//An ID counts once per multiplicity, no matter how many letters repeat that many times
+fn histogram<'a, I: Iterator<Item = &'a str>>(ids: I, multiplicities: &[usize]) -> Histogram {
+    let mut tallies = multiplicities
+        .iter()
+        .map(|m| (*m, 0))
+        .collect::<BTreeMap<_, _>>();
+    for id in ids {
+        let found = counts(id.chars()).values().cloned().collect::<HashSet<_>>();
+        for (multiplicity, tally) in tallies.iter_mut() {
+            if found.contains(multiplicity) {
+                *tally += 1;
+            }
+        }
+    }
+    Histogram { tallies }
+}
+
+//Two IDs of the same length that differ in at most k positions
+struct SimilarPair {
+    first: usize,
+    second: usize,
+    //Positions at which the IDs differ
+    positions: Vec<usize>,
+}
+
+impl SimilarPair {
+    fn common(&self, input: &[Vec<char>]) -> String {
+        input[self.first]
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| !self.positions.contains(i))
+            .map(|(_, ch)| *ch)
+            .collect()
+    }
+}
+
+//Splits `len` characters into `count` consecutive segments of almost equal size
+fn segments(len: usize, count: usize) -> Vec<Range<usize>> {
+    (0..count)
+        .map(|s| (s * len / count)..((s + 1) * len / count))
+        .collect()
+}
+
+//All pairs of IDs within Hamming distance k, ordered by their indexes.
+//If two IDs differ in at most k positions, at least one of their k + 1 segments
+//is the same, so we only compare IDs that share a segment
+fn find_similar(input: &[Vec<char>], k: usize) -> Vec<SimilarPair> {
+    let mut index: HashMap<(usize, usize, &[char]), Vec<usize>> = HashMap::new();
+    for (i, id) in input.iter().enumerate() {
+        for (s, range) in segments(id.len(), k + 1).into_iter().enumerate() {
+            index.entry((id.len(), s, &id[range])).or_default().push(i);
+        }
+    }
+    let mut compared = HashSet::new();
+    let mut result = vec![];
+    for bucket in index.values() {
+        for (n, first) in bucket.iter().enumerate() {
+            for second in &bucket[n + 1..] {
+                if !compared.insert((*first, *second)) {
+                    continue;
+                }
+                let positions = input[*first]
+                    .iter()
+                    .zip(input[*second].iter())
+                    .enumerate()
+                    .filter(|(_, (a, b))| a != b)
+                    .map(|(i, _)| i)
+                    .take(k + 1)
+                    .collect::<Vec<_>>();
+                if positions.len() <= k {
+                    result.push(SimilarPair {
+                        first: *first,
+                        second: *second,
+                        positions,
+                    })
+                }
+            }
+        }
+    }
+    result.sort_by_key(|pair| (pair.first, pair.second));
+    result
+}
+
+//Two IDs, possibly of different length, and the letters they share
+struct EditPair {
+    first: usize,
+    second: usize,
+    distance: usize,
+    //Letters kept in place by the cheapest sequence of edits
+    common: String,
+}
+
+//Levenshtein distance between two IDs along with the letters left untouched by it
+fn levenshtein(a: &[char], b: &[char]) -> (usize, String) {
+    //table[i][j] is the distance between the first i letters of a and the first j of b
+    let mut table = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for i in 0..=a.len() {
+        for j in 0..=b.len() {
+            table[i][j] = if i == 0 || j == 0 {
+                i + j
+            } else {
+                let substitution = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+                (table[i - 1][j - 1] + substitution)
+                    .min(table[i - 1][j] + 1)
+                    .min(table[i][j - 1] + 1)
+            }
+        }
+    }
+    //Walk back from the corner, collecting letters matched on the way
+    let mut common = vec![];
+    let (mut i, mut j) = (a.len(), b.len());
+    while i > 0 && j > 0 {
+        if a[i - 1] == b[j - 1] && table[i][j] == table[i - 1][j - 1] {
+            common.push(a[i - 1]);
+            i -= 1;
+            j -= 1;
+        } else if table[i][j] == table[i - 1][j - 1] + 1 {
+            i -= 1;
+            j -= 1;
+        } else if table[i][j] == table[i - 1][j] + 1 {
+            i -= 1;
+        } else {
+            j -= 1;
+        }
+    }
+    (table[a.len()][b.len()], common.into_iter().rev().collect())
+}
+
+//All pairs of IDs with the smallest edit distance, ordered by their indexes.
+//Pairs further apart than `max_distance` are never reported
+fn find_closest(input: &[Vec<char>], max_distance: Option<usize>) -> Vec<EditPair> {
+    let mut best = max_distance;
+    let mut result: Vec<EditPair> = vec![];
+    for (first, a) in input.iter().enumerate() {
+        for (second, b) in input.iter().enumerate().skip(first + 1) {
+            //Length difference alone takes that many insertions
+            let gap = a.len().max(b.len()) - a.len().min(b.len());
+            if best.is_some_and(|best| gap > best) {
+                continue;
             }
+            let (distance, common) = levenshtein(a, b);
+            if best.is_some_and(|best| distance > best) {
+                continue;
+            }
+            if best.is_some_and(|best| distance < best) {
+                result.clear();
+            }
+            best = Some(distance);
+            result.push(EditPair {
+                first,
+                second,
+                distance,
+                common,
+            });
         }
     }
-    panic!("Unable to find a match!")
+    result
+}
+
+#[test]
+fn test_levenshtein() {
+    let chars = |s: &str| s.chars().collect::<Vec<_>>();
+    assert_eq!(
+        levenshtein(&chars("fghij"), &chars("fguij")),
+        (1, "fgij".to_string())
+    );
+    assert_eq!(
+        levenshtein(&chars("kitten"), &chars("sitting")),
+        (3, "ittn".to_string())
+    );
+    assert_eq!(levenshtein(&chars(""), &chars("abc")), (3, "".to_string()));
+
+    let input = ["abcde", "fghij", "abde", "fguijk"]
+        .iter()
+        .map(|id| chars(id))
+        .collect::<Vec<_>>();
+    let pairs = find_closest(&input, None);
+    assert_eq!(
+        pairs
+            .iter()
+            .map(|pair| (pair.first, pair.second, pair.distance, pair.common.as_ref()))
+            .collect::<Vec<_>>(),
+        vec![(0, 2, 1, "abde")]
+    );
+    assert!(find_closest(&input, Some(0)).is_empty());
+}
+
+#[test]
+fn test_histogram() {
+    let input = [
+        "abcdef", "bababc", "abbcde", "abcccd", "aabcdd", "abcdee", "ababab",
+    ];
+    let tallies = histogram(input.iter().cloned(), &[1, 2, 3, 4]);
+    assert_eq!(
+        tallies.tallies.into_iter().collect::<Vec<_>>(),
+        vec![(1, 6), (2, 4), (3, 3), (4, 0)]
+    );
+    let tallies = histogram(input.iter().cloned(), &[2, 3]);
+    assert_eq!(tallies.checksum(&[2, 3]), Some(12));
+    assert_eq!(tallies.checksum(&[3]), Some(3));
+    assert_eq!(tallies.checksum(&[5]), None);
+}
+
+#[test]
+fn test_find_similar() {
+    let input = [
+        "abcde", "fghij", "klmno", "pqrst", "fguij", "axcye", "wvxyz",
+    ]
+    .iter()
+    .map(|id| id.chars().collect())
+    .collect::<Vec<_>>();
+    let pairs = find_similar(&input, 1);
+    assert_eq!(pairs.len(), 1);
+    assert_eq!((pairs[0].first, pairs[0].second), (1, 4));
+    assert_eq!(pairs[0].positions, vec![2]);
+    assert_eq!(pairs[0].common(&input), "fgij");
+
+    let pairs = find_similar(&input, 2);
+    assert_eq!(
+        pairs
+            .iter()
+            .map(|pair| (pair.first, pair.second))
+            .collect::<Vec<_>>(),
+        vec![(0, 5), (1, 4)]
+    );
 }
@@ -1,5 +1,10 @@
+use std::cmp::Reverse;
 use std::collections::HashSet;
-use std::io::{self, Read};
+use std::env;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::process;
 use std::str::FromStr;
 
 struct Claim {
@@ -10,69 +15,735 @@ struct Claim {
     y1: usize,
 }
 
+//What went wrong and where. Columns are counted in characters from 1
+#[derive(Debug, PartialEq)]
+struct ClaimError {
+    line: usize,
+    column: usize,
+    message: String,
+}
+
+impl fmt::Display for ClaimError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Line {}, column {}: {}",
+            self.line, self.column, self.message
+        )
+    }
+}
+
+//Walks over a single claim line, whitespace is allowed between any two tokens
+struct Cursor<'a> {
+    chars: Vec<(usize, char)>,
+    line: &'a str,
+    pos: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn new(line: &'a str) -> Self {
+        Self {
+            chars: line.char_indices().collect(),
+            line,
+            pos: 0,
+        }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.pos < self.chars.len() && self.chars[self.pos].1.is_whitespace() {
+            self.pos += 1
+        }
+    }
+
+    fn error(&self, expected: &str) -> ClaimError {
+        let found = match self.chars.get(self.pos) {
+            Some((_, ch)) => format!("'{}'", ch),
+            None => "end of line".to_string(),
+        };
+        ClaimError {
+            line: 1,
+            column: self.pos + 1,
+            message: format!("expected {}, found {}", expected, found),
+        }
+    }
+
+    fn token(&mut self, token: char) -> Result<(), ClaimError> {
+        self.skip_whitespace();
+        match self.chars.get(self.pos) {
+            Some((_, ch)) if *ch == token => {
+                self.pos += 1;
+                Ok(())
+            }
+            _ => Err(self.error(&format!("'{}'", token))),
+        }
+    }
+
+    fn number(&mut self, name: &str) -> Result<usize, ClaimError> {
+        self.skip_whitespace();
+        let start = self.pos;
+        while self.pos < self.chars.len() && self.chars[self.pos].1.is_ascii_digit() {
+            self.pos += 1
+        }
+        if start == self.pos {
+            return Err(self.error(name));
+        }
+        let end = self.chars.get(self.pos).map_or(self.line.len(), |c| c.0);
+        self.line[self.chars[start].0..end]
+            .parse()
+            .map_err(|_| ClaimError {
+                line: 1,
+                column: start + 1,
+                message: format!("{} is too large", name),
+            })
+    }
+
+    fn end(&mut self) -> Result<(), ClaimError> {
+        self.skip_whitespace();
+        if self.pos < self.chars.len() {
+            return Err(self.error("end of line"));
+        }
+        Ok(())
+    }
+}
+
 impl FromStr for Claim {
-    type Err = String;
+    type Err = ClaimError;
 
+    //#id @ x,y: WxH
     fn from_str(line: &str) -> Result<Self, Self::Err> {
-        let chunks: Vec<_> = line.split(" ").collect();
-        let id = chunks[0].trim_matches('#').parse().unwrap();
-        let start: Vec<_> = chunks[2]
-            .trim_matches(':')
-            .split(",")
-            .map(|x| x.parse().unwrap())
-            .collect();
-        let (x0, y0) = (start[0], start[1]);
-        let dimensions: Vec<usize> = chunks[3].split("x").map(|x| x.parse().unwrap()).collect();
-        let (x1, y1) = (x0 + dimensions[0], y0 + dimensions[1]);
+        let mut cursor = Cursor::new(line);
+        cursor.token('#')?;
+        let id = cursor.number("claim id")?;
+        cursor.token('@')?;
+        let x0 = cursor.number("left offset")?;
+        cursor.token(',')?;
+        let y0 = cursor.number("top offset")?;
+        cursor.token(':')?;
+        cursor.skip_whitespace();
+        let size_column = cursor.pos + 1;
+        let width = cursor.number("width")?;
+        cursor.token('x')?;
+        let height = cursor.number("height")?;
+        cursor.end()?;
+        if width == 0 || height == 0 {
+            return Err(ClaimError {
+                line: 1,
+                column: size_column,
+                message: format!("claim #{} has zero size {}x{}", id, width, height),
+            });
+        }
+        let (x1, y1) = match (x0.checked_add(width), y0.checked_add(height)) {
+            (Some(x1), Some(y1)) => (x1, y1),
+            _ => {
+                return Err(ClaimError {
+                    line: 1,
+                    column: size_column,
+                    message: format!("claim #{} doesn't fit on the fabric", id),
+                })
+            }
+        };
         Ok(Self { id, x0, y0, x1, y1 })
     }
 }
 
+//One claim per line, blank lines are skipped
+fn parse_claims(input: &str) -> Result<Vec<Claim>, ClaimError> {
+    let mut ids = HashSet::new();
+    let mut claims = vec![];
+    for (i, line) in input.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let claim: Claim = line
+            .parse()
+            .map_err(|e: ClaimError| ClaimError { line: i + 1, ..e })?;
+        if !ids.insert(claim.id) {
+            return Err(ClaimError {
+                line: i + 1,
+                column: line.chars().position(|ch| ch == '#').unwrap() + 1,
+                message: format!("duplicate claim id #{}", claim.id),
+            });
+        }
+        claims.push(claim);
+    }
+    Ok(claims)
+}
+
 fn main() {
     let mut input = String::new();
     io::stdin().read_to_string(&mut input).unwrap();
-    let claims: Vec<Claim> = input
-        .split("\n")
-        .map(|line| line.parse().unwrap())
-        .collect();
-    println!("Overlaps count is {}", find_overlaps(&claims));
-    println!("Good claim is {}", find_non_overlaping(&claims));
-}
-
-fn fabric<'a>(claims: &'a Vec<Claim>) -> Vec<Vec<Vec<&'a Claim>>> {
-    let mut fabric: Vec<Vec<Vec<&Claim>>> = (0..1000)
-        .map(|_| (0..1000).map(|_| vec![]).collect())
-        .collect();
-    claims.iter().for_each(|claim| {
-        (claim.x0..claim.x1)
-            .for_each(|x| (claim.y0..claim.y1).for_each(|y| fabric[x][y].push(&claim)))
-    });
-    fabric
-}
-
-fn find_overlaps(claims: &Vec<Claim>) -> usize {
-    fabric(claims)
-        .iter()
-        .map(|row| row.iter().filter(|claims| claims.len() > 1).count())
-        .sum()
-}
-
-fn find_non_overlaping(claims: &Vec<Claim>) -> usize {
-    let mut bad_claims = HashSet::new();
-    fabric(claims).iter().for_each(|row| {
-        row.iter()
-            .filter(|claims| claims.len() > 1)
-            .for_each(|claims| {
-                claims.iter().for_each(|claim| {
-                    bad_claims.insert(claim.id);
-                })
+    let claims = match parse_claims(&input) {
+        Ok(claims) => claims,
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+    };
+    let mut sweep = false;
+    let mut heatmap_path = None;
+    let mut command = vec![];
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_ref() {
+            "--sweep" => sweep = true,
+            "--heatmap" => heatmap_path = Some(args.next().expect("Image path expected")),
+            _ => command.push(arg),
+        }
+    }
+    if let Some(path) = heatmap_path {
+        //Grayscale for .pgm, otherwise colour with the intact claim in green
+        let colour = !path.ends_with(".pgm");
+        let fabric = Fabric::new(&claims).unwrap_or_else(|e| {
+            eprintln!("{}", e);
+            process::exit(1)
+        });
+        let intact = claims.iter().find(|c| fabric.intact(c));
+        File::create(&path)
+            .and_then(|mut file| file.write_all(&heatmap(&fabric, intact, colour)))
+            .expect("Failed to write the image");
+    }
+    let command = command.iter().map(|arg| arg.as_str()).collect::<Vec<_>>();
+    match command.as_slice() {
+        [] => {
+            let fail = |e: String| -> usize {
+                eprintln!("{}", e);
+                process::exit(1)
+            };
+            //Sweep mode never rasterises, so it works for claims of any size
+            if sweep {
+                println!("Overlaps count is {}", sweep_overlaps(&claims));
+                println!(
+                    "Good claim is {}",
+                    isolated_claim(&claims).unwrap_or_else(fail)
+                );
+            } else {
+                println!(
+                    "Overlaps count is {}",
+                    find_overlaps(&claims).unwrap_or_else(fail)
+                );
+                println!(
+                    "Good claim is {}",
+                    find_non_overlaping(&claims).unwrap_or_else(fail)
+                );
+            }
+        }
+        ["at", point] => {
+            let point = point
+                .split(',')
+                .map(|x| x.trim().parse::<usize>())
+                .collect::<Result<Vec<_>, _>>();
+            let point = match point {
+                Ok(point) if point.len() == 2 => point,
+                _ => {
+                    eprintln!("Usage: day3 at X,Y");
+                    process::exit(1);
+                }
+            };
+            let ids = claims
+                .iter()
+                .filter(|c| c.contains(point[0], point[1]))
+                .map(|c| format!("#{}", c.id))
+                .collect::<Vec<_>>();
+            if ids.is_empty() {
+                println!("No claims cover {},{}", point[0], point[1]);
+            } else {
+                println!("{}", ids.join(" "));
+            }
+        }
+        ["overlaps", id] => {
+            let id = id
+                .trim_matches('#')
+                .parse()
+                .expect("Claim id should be a number");
+            let graph = OverlapGraph::new(&claims);
+            let index = claims
+                .iter()
+                .position(|c| c.id == id)
+                .expect("No such claim");
+            if graph.of(index).next().is_none() {
+                println!("Claim #{} doesn't overlap any other", id);
+            }
+            for overlap in graph.of(index) {
+                println!("{}", overlap);
+            }
+        }
+        ["components"] => {
+            let graph = OverlapGraph::new(&claims);
+            for component in graph.components() {
+                let ids = component
+                    .iter()
+                    .map(|i| format!("#{}", claims[*i].id))
+                    .collect::<Vec<_>>();
+                println!("{} claims: {}", component.len(), ids.join(" "));
+                for i in component.iter() {
+                    for overlap in graph.of(*i).filter(|o| o.first == claims[*i].id) {
+                        println!("  {}", overlap);
+                    }
+                }
+            }
+        }
+        _ => panic!("Usage: day3 [--sweep] [--heatmap IMAGE] [at X,Y | overlaps ID | components]"),
+    }
+}
+
+impl Claim {
+    fn contains(&self, x: usize, y: usize) -> bool {
+        self.x0 <= x && x < self.x1 && self.y0 <= y && y < self.y1
+    }
+
+    //Rectangle shared by both claims, if any
+    fn overlap(&self, other: &Claim) -> Option<Overlap> {
+        let (x0, x1) = (self.x0.max(other.x0), self.x1.min(other.x1));
+        let (y0, y1) = (self.y0.max(other.y0), self.y1.min(other.y1));
+        if x0 < x1 && y0 < y1 {
+            Some(Overlap {
+                first: self.id.min(other.id),
+                second: self.id.max(other.id),
+                x0,
+                y0,
+                x1,
+                y1,
             })
-    });
+        } else {
+            None
+        }
+    }
+}
+
+//Two claims sharing some fabric, printed in the claim format: #1 #3 @ 3,3: 2x2
+struct Overlap {
+    first: usize,
+    second: usize,
+    x0: usize,
+    y0: usize,
+    x1: usize,
+    y1: usize,
+}
+
+impl fmt::Display for Overlap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "#{} #{} @ {},{}: {}x{}",
+            self.first,
+            self.second,
+            self.x0,
+            self.y0,
+            self.x1 - self.x0,
+            self.y1 - self.y0
+        )
+    }
+}
+
+//Claims are vertices, connected if they overlap
+struct OverlapGraph {
+    overlaps: Vec<Overlap>,
+    //For every claim: indexes of the claims it overlaps and of the overlaps themselves
+    edges: Vec<Vec<(usize, usize)>>,
+}
+
+impl OverlapGraph {
+    fn new(claims: &[Claim]) -> Self {
+        //Claims sorted by the left edge, so we stop looking once they're too far right
+        let mut order = (0..claims.len()).collect::<Vec<_>>();
+        order.sort_by_key(|i| claims[*i].x0);
+        let mut graph = Self {
+            overlaps: vec![],
+            edges: vec![vec![]; claims.len()],
+        };
+        for (n, i) in order.iter().enumerate() {
+            for j in order[n + 1..]
+                .iter()
+                .take_while(|j| claims[**j].x0 < claims[*i].x1)
+            {
+                if let Some(overlap) = claims[*i].overlap(&claims[*j]) {
+                    graph.edges[*i].push((*j, graph.overlaps.len()));
+                    graph.edges[*j].push((*i, graph.overlaps.len()));
+                    graph.overlaps.push(overlap);
+                }
+            }
+        }
+        for edges in graph.edges.iter_mut() {
+            edges.sort();
+        }
+        graph
+    }
+
+    //Overlaps of the claim with the given index
+    fn of(&self, claim: usize) -> impl Iterator<Item = &Overlap> {
+        self.edges[claim]
+            .iter()
+            .map(move |(_, overlap)| &self.overlaps[*overlap])
+    }
+
+    //Groups of claim indexes connected through overlaps, largest first
+    fn components(&self) -> Vec<Vec<usize>> {
+        let mut seen = vec![false; self.edges.len()];
+        let mut components = vec![];
+        for start in 0..self.edges.len() {
+            if seen[start] {
+                continue;
+            }
+            seen[start] = true;
+            let mut component = vec![start];
+            let mut n = 0;
+            while n < component.len() {
+                for (next, _) in self.edges[component[n]].iter() {
+                    if !seen[*next] {
+                        seen[*next] = true;
+                        component.push(*next);
+                    }
+                }
+                n += 1;
+            }
+            component.sort();
+            components.push(component);
+        }
+        components.sort_by_key(|c| Reverse(c.len()));
+        components
+    }
+}
+
+//Number of claims covering every square inch of the claims' bounding box
+struct Fabric {
+    x0: usize,
+    y0: usize,
+    width: usize,
+    counts: Vec<usize>,
+}
+
+impl Fabric {
+    //Fails if the bounding box is too large to keep a count per square inch
+    fn new(claims: &[Claim]) -> Result<Self, String> {
+        let x0 = claims.iter().map(|c| c.x0).min().unwrap_or(0);
+        let y0 = claims.iter().map(|c| c.y0).min().unwrap_or(0);
+        let width = claims.iter().map(|c| c.x1).max().unwrap_or(x0) - x0;
+        let height = claims.iter().map(|c| c.y1).max().unwrap_or(y0) - y0;
+        let too_large = || {
+            format!(
+                "Fabric of {}x{} inches is too large to rasterise, try --sweep",
+                width, height
+            )
+        };
+        let cells = width.checked_mul(height).ok_or_else(too_large)?;
+        let mut counts = vec![];
+        counts.try_reserve_exact(cells).map_err(|_| too_large())?;
+        counts.resize(cells, 0);
+        let mut fabric = Self {
+            x0,
+            y0,
+            width,
+            counts,
+        };
+        for claim in claims {
+            for y in claim.y0..claim.y1 {
+                let row = fabric.index(claim.x0, y);
+                fabric.counts[row..row + claim.x1 - claim.x0]
+                    .iter_mut()
+                    .for_each(|count| *count += 1);
+            }
+        }
+        Ok(fabric)
+    }
+
+    fn height(&self) -> usize {
+        self.counts.len().checked_div(self.width).unwrap_or(0)
+    }
+
+    fn index(&self, x: usize, y: usize) -> usize {
+        (y - self.y0) * self.width + x - self.x0
+    }
+
+    //True if no other claim touches any square inch of this one
+    fn intact(&self, claim: &Claim) -> bool {
+        (claim.y0..claim.y1).all(|y| {
+            let row = self.index(claim.x0, y);
+            self.counts[row..row + claim.x1 - claim.x0]
+                .iter()
+                .all(|count| *count == 1)
+        })
+    }
+}
+
+//Binary Netpbm image of the bounding box, brighter where more claims overlap.
+//PPM with the intact claim painted green if `colour` is set, PGM otherwise
+fn heatmap(fabric: &Fabric, intact: Option<&Claim>, colour: bool) -> Vec<u8> {
+    let (width, height) = (fabric.width, fabric.height());
+    let magic = if colour { "P6" } else { "P5" };
+    let mut image = format!("{}\n{} {}\n255\n", magic, width, height).into_bytes();
+    let max = fabric.counts.iter().cloned().max().unwrap_or(0).max(1);
+    for y in 0..height {
+        for x in 0..width {
+            let count = fabric.counts[y * width + x];
+            let level = (count * 255 / max) as u8;
+            if !colour {
+                image.push(level);
+            } else if intact.is_some_and(|c| c.contains(x + fabric.x0, y + fabric.y0)) {
+                image.extend_from_slice(&[0, 255, 0]);
+            } else {
+                image.extend_from_slice(&[level, level, level]);
+            }
+        }
+    }
+    image
+}
+
+fn find_overlaps(claims: &[Claim]) -> Result<usize, String> {
+    Ok(Fabric::new(claims)?
+        .counts
+        .iter()
+        .filter(|count| **count > 1)
+        .count())
+}
+
+fn find_non_overlaping(claims: &[Claim]) -> Result<usize, String> {
+    let fabric = Fabric::new(claims)?;
     claims
         .iter()
-        .filter(|c| !bad_claims.contains(&c.id))
-        .next()
-        .ok_or("No good claims found")
-        .unwrap()
-        .id
+        .find(|c| fabric.intact(c))
+        .map(|c| c.id)
+        .ok_or_else(|| "No good claims found".to_string())
+}
+
+//Same as find_non_overlaping, but looks for a claim without edges in the overlap graph
+fn isolated_claim(claims: &[Claim]) -> Result<usize, String> {
+    let graph = OverlapGraph::new(claims);
+    (0..claims.len())
+        .find(|i| graph.of(*i).next().is_none())
+        .map(|i| claims[i].id)
+        .ok_or_else(|| "No good claims found".to_string())
+}
+
+//Segment tree over the gaps between sorted y coordinates.
+//Keeps how much of y is covered by at least one and at least two claims
+struct Coverage {
+    ys: Vec<usize>,
+    //Claims covering the whole node that aren't pushed down to its children
+    claims: Vec<usize>,
+    once: Vec<usize>,
+    twice: Vec<usize>,
+}
+
+impl Coverage {
+    fn new(ys: Vec<usize>) -> Self {
+        let nodes = 4 * ys.len().max(1);
+        Self {
+            ys,
+            claims: vec![0; nodes],
+            once: vec![0; nodes],
+            twice: vec![0; nodes],
+        }
+    }
+
+    //Adds (delta = 1) or removes (delta = -1) a claim spanning [y0, y1)
+    fn update(&mut self, y0: usize, y1: usize, delta: isize) {
+        let from = self.ys.binary_search(&y0).unwrap();
+        let to = self.ys.binary_search(&y1).unwrap();
+        if from < to {
+            self.update_node(1, 0, self.ys.len() - 1, from, to, delta);
+        }
+    }
+
+    //Node covers ys[l]..ys[r]
+    fn update_node(
+        &mut self,
+        node: usize,
+        l: usize,
+        r: usize,
+        from: usize,
+        to: usize,
+        delta: isize,
+    ) {
+        if to <= l || r <= from {
+            return;
+        }
+        if from <= l && r <= to {
+            self.claims[node] = (self.claims[node] as isize + delta) as usize;
+        } else {
+            let mid = (l + r) / 2;
+            self.update_node(2 * node, l, mid, from, to, delta);
+            self.update_node(2 * node + 1, mid, r, from, to, delta);
+        }
+        let full = self.ys[r] - self.ys[l];
+        let leaf = r - l == 1;
+        let (once, twice) = if leaf {
+            (0, 0)
+        } else {
+            (
+                self.once[2 * node] + self.once[2 * node + 1],
+                self.twice[2 * node] + self.twice[2 * node + 1],
+            )
+        };
+        match self.claims[node] {
+            0 => {
+                self.once[node] = once;
+                self.twice[node] = twice;
+            }
+            1 => {
+                self.once[node] = full;
+                self.twice[node] = once;
+            }
+            _ => {
+                self.once[node] = full;
+                self.twice[node] = full;
+            }
+        }
+    }
+
+    fn covered_twice(&self) -> usize {
+        self.twice[1]
+    }
+}
+
+//Same as find_overlaps, but sweeps a vertical line over the claims' left and right edges
+//instead of visiting every square inch
+fn sweep_overlaps(claims: &[Claim]) -> usize {
+    let mut ys = claims
+        .iter()
+        .flat_map(|c| vec![c.y0, c.y1])
+        .collect::<Vec<_>>();
+    ys.sort();
+    ys.dedup();
+    let mut edges = claims
+        .iter()
+        .filter(|c| c.x0 < c.x1 && c.y0 < c.y1)
+        .flat_map(|c| vec![(c.x0, 1, c.y0, c.y1), (c.x1, -1, c.y0, c.y1)])
+        .collect::<Vec<_>>();
+    edges.sort();
+    let mut coverage = Coverage::new(ys);
+    let mut area = 0;
+    let mut last_x = edges.first().map_or(0, |e| e.0);
+    for (x, delta, y0, y1) in edges {
+        area += (x - last_x) * coverage.covered_twice();
+        coverage.update(y0, y1, delta);
+        last_x = x;
+    }
+    area
+}
+
+#[cfg(test)]
+fn claims(lines: &[&str]) -> Vec<Claim> {
+    lines.iter().map(|line| line.parse().unwrap()).collect()
+}
+
+#[test]
+fn test_parse_claims() {
+    let claims = parse_claims("#123 @ 3,2: 5x4\n  #7@0 , 1 :2 x 3  \n\n").unwrap();
+    assert_eq!(claims.len(), 2);
+    assert_eq!(
+        (
+            claims[1].id,
+            claims[1].x0,
+            claims[1].y0,
+            claims[1].x1,
+            claims[1].y1
+        ),
+        (7, 0, 1, 2, 4)
+    );
+
+    let error = |input: &str| parse_claims(input).err().unwrap().to_string();
+    assert_eq!(
+        error("#1 @ 1,3: 4x4\n#2 @ 3;1: 4x4"),
+        "Line 2, column 7: expected ',', found ';'"
+    );
+    assert_eq!(
+        error("#1 @ 1,3: 4x"),
+        "Line 1, column 13: expected height, found end of line"
+    );
+    assert_eq!(
+        error("1 @ 1,3: 4x4"),
+        "Line 1, column 1: expected '#', found '1'"
+    );
+    assert_eq!(
+        error("#1 @ 1,3: 4x4 #2"),
+        "Line 1, column 15: expected end of line, found '#'"
+    );
+    assert_eq!(
+        error("#1 @ 1,3: 0x4"),
+        "Line 1, column 11: claim #1 has zero size 0x4"
+    );
+    assert_eq!(
+        error("#1 @ 1,3: 4x4\n\n #1 @ 5,5: 1x1"),
+        "Line 3, column 2: duplicate claim id #1"
+    );
+}
+
+#[test]
+fn test_overlap_graph() {
+    let claims = claims(&[
+        "#1 @ 1,3: 4x4",
+        "#2 @ 3,1: 4x4",
+        "#3 @ 5,5: 2x2",
+        "#4 @ 6,6: 3x3",
+        "#5 @ 20,20: 1x1",
+    ]);
+    let graph = OverlapGraph::new(&claims);
+    assert_eq!(
+        graph.of(2).map(|o| o.to_string()).collect::<Vec<_>>(),
+        vec!["#3 #4 @ 6,6: 1x1"]
+    );
+    assert_eq!(
+        graph.of(0).map(|o| o.to_string()).collect::<Vec<_>>(),
+        vec!["#1 #2 @ 3,3: 2x2"]
+    );
+    assert_eq!(graph.components(), vec![vec![0, 1], vec![2, 3], vec![4]]);
+    assert!(claims[1].contains(6, 4));
+    assert!(!claims[1].contains(7, 4));
+}
+
+#[test]
+fn test_sweep_matches_fabric() {
+    let puzzle = claims(&include_str!("../input").lines().collect::<Vec<_>>());
+    assert_eq!(Ok(sweep_overlaps(&puzzle)), find_overlaps(&puzzle));
+
+    //Too large to rasterise
+    let huge = claims(&[
+        "#1 @ 0,0: 3000000x2000000",
+        "#2 @ 1000000,1000000: 4000000x4000000",
+        "#3 @ 9000000,0: 5x5",
+    ]);
+    assert_eq!(sweep_overlaps(&huge), 2000000 * 1000000);
+    assert_eq!(isolated_claim(&huge), Ok(3));
+    assert_eq!(
+        isolated_claim(&huge[..2]),
+        Err("No good claims found".to_string())
+    );
+}
+
+#[test]
+fn test_heatmap() {
+    let claims = claims(&["#1 @ 1,3: 4x4", "#2 @ 3,1: 4x4", "#3 @ 5,5: 2x2"]);
+    let fabric = Fabric::new(&claims).unwrap();
+    let header = "P5\n6 6\n255\n".len();
+    let image = heatmap(&fabric, None, false);
+    assert!(image.starts_with(b"P5\n6 6\n255\n"));
+    //Bounding box starts at 1,1: the overlap is at 2,2 and nothing covers 0,0
+    assert_eq!(image[header..header + 6], [0, 0, 127, 127, 127, 127]);
+    assert_eq!(image[header + 2 * 6 + 2], 255);
+
+    let image = heatmap(&fabric, Some(&claims[2]), true);
+    assert!(image.starts_with(b"P6\n6 6\n255\n"));
+    assert_eq!(image.len(), header + 6 * 6 * 3);
+    let pixel = |x: usize, y: usize| &image[header + (y * 6 + x) * 3..header + (y * 6 + x) * 3 + 3];
+    assert_eq!(pixel(4, 4), [0, 255, 0]);
+    assert_eq!(pixel(2, 2), [255, 255, 255]);
+}
+
+#[test]
+fn test_fabric() {
+    let example = claims(&["#1 @ 1,3: 4x4", "#2 @ 3,1: 4x4", "#3 @ 5,5: 2x2"]);
+    assert_eq!(find_overlaps(&example), Ok(4));
+    assert_eq!(find_non_overlaping(&example), Ok(3));
+
+    //Far away from the origin
+    let far = claims(&["#1 @ 5000000,7000000: 3x2", "#2 @ 5000002,7000001: 2x2"]);
+    let fabric = Fabric::new(&far).unwrap();
+    assert_eq!((fabric.x0, fabric.y0, fabric.width), (5000000, 7000000, 4));
+    assert_eq!(fabric.counts, vec![1, 1, 1, 0, 1, 1, 2, 1, 0, 0, 1, 1]);
+    assert_eq!(find_overlaps(&far), Ok(1));
+
+    //Bounding box has more square inches than fit in usize
+    let spread = claims(&["#1 @ 0,0: 1x1", "#2 @ 3000000000,3000000000: 1x1"]);
+    assert!(Fabric::new(&spread).is_err());
+    assert_eq!(sweep_overlaps(&spread), 0);
 }
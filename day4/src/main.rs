@@ -1,129 +1,645 @@
 extern crate chrono;
 use chrono::prelude::*;
-use chrono::Duration;
-use std::collections::HashMap;
-use std::error::Error;
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt;
 use std::io::{self, Read};
+use std::process;
 use std::str::FromStr;
 
 struct Schedule {
     naps: Vec<Nap>,
+    //Night and guard of every shift in chronological order, even if nobody slept
+    shifts: Vec<(NaiveDate, u16)>,
+}
+
+#[derive(Debug, PartialEq)]
+enum ScheduleError {
+    Malformed { line: usize, text: String },
+    //Falls asleep while already asleep
+    DoubleSleep { line: usize, text: String },
+    WakeWithoutSleep { line: usize, text: String },
+    //Falls asleep and the shift ends (or the log does) before waking up
+    SleepNeverEnded { line: usize, text: String },
+    //Falls asleep or wakes up before any guard began a shift
+    UnknownGuard { line: usize, text: String },
+}
+
+impl fmt::Display for ScheduleError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ScheduleError::Malformed { line, text } => {
+                write!(f, "Line {}: malformed record \"{}\"", line, text)
+            }
+            ScheduleError::DoubleSleep { line, text } => write!(
+                f,
+                "Line {}: guard falls asleep while already asleep \"{}\"",
+                line, text
+            ),
+            ScheduleError::WakeWithoutSleep { line, text } => write!(
+                f,
+                "Line {}: guard wakes up without falling asleep \"{}\"",
+                line, text
+            ),
+            ScheduleError::SleepNeverEnded { line, text } => write!(
+                f,
+                "Line {}: guard falls asleep and never wakes up \"{}\"",
+                line, text
+            ),
+            ScheduleError::UnknownGuard { line, text } => {
+                write!(f, "Line {}: no guard is on duty \"{}\"", line, text)
+            }
+        }
+    }
+}
+
+enum Event {
+    BeginsShift(u16),
+    FallsAsleep,
+    WakesUp,
+}
+
+//Single line of the log, remembering where it came from
+struct Record<'a> {
+    line: usize,
+    text: &'a str,
+    time: NaiveDateTime,
+    event: Event,
+}
+
+impl<'a> Record<'a> {
+    //[1518-11-01 00:00] Guard #10 begins shift
+    fn parse(line: usize, text: &'a str) -> Result<Self, ScheduleError> {
+        let malformed = || ScheduleError::Malformed {
+            line,
+            text: text.to_string(),
+        };
+        let trimmed = text.trim();
+        if !trimmed.starts_with('[') {
+            return Err(malformed());
+        }
+        let mut chunks = trimmed[1..].splitn(2, ']');
+        let (stamp, message) = match (chunks.next(), chunks.next()) {
+            (Some(stamp), Some(message)) => (stamp, message.trim()),
+            _ => return Err(malformed()),
+        };
+        let time =
+            NaiveDateTime::parse_from_str(stamp, "%Y-%m-%d %H:%M").map_err(|_| malformed())?;
+        let event = match message {
+            "falls asleep" => Event::FallsAsleep,
+            "wakes up" => Event::WakesUp,
+            message => {
+                let words: Vec<_> = message.split_whitespace().collect();
+                match words.as_slice() {
+                    ["Guard", id, "begins", "shift"] if id.starts_with('#') => {
+                        Event::BeginsShift(id[1..].parse().map_err(|_| malformed())?)
+                    }
+                    _ => return Err(malformed()),
+                }
+            }
+        };
+        Ok(Self {
+            line,
+            text,
+            time,
+            event,
+        })
+    }
+}
+
+//Night the shift belongs to. Guards may show up a bit before midnight.
+//None if that night is past the last date chrono knows
+fn shift_date(time: NaiveDateTime) -> Option<NaiveDate> {
+    if time.hour() == 23 {
+        time.date().succ_opt()
+    } else {
+        Some(time.date())
+    }
 }
 
 impl FromStr for Schedule {
-    type Err = Box<Error>;
+    type Err = ScheduleError;
 
+    //Records may come in any order, blank lines are skipped
     fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let mut records = input
+            .lines()
+            .enumerate()
+            .filter(|(_, text)| !text.trim().is_empty())
+            .map(|(i, text)| Record::parse(i + 1, text))
+            .collect::<Result<Vec<_>, _>>()?;
+        records.sort_by_key(|record| record.time);
+        let error = |record: &Record, kind: fn(usize, String) -> ScheduleError| {
+            kind(record.line, record.text.to_string())
+        };
         let mut naps = vec![];
-        let mut input_vector: Vec<_> = input.split("\n").collect();
-        input_vector.sort();
-        let mut current_guard: u16 = 0;
-        let mut nap_start = Utc::now().naive_local();
-        input_vector.iter().for_each(|line| {
-            let chunks: Vec<_> = line.trim_matches('[').split("] ").collect();
-            let ch = chunks[0].replace("1518", "2018");
-            let ts = NaiveDateTime::parse_from_str(&ch, "%Y-%m-%d %H:%M").unwrap();
-            match chunks[1] {
-                "falls asleep" => nap_start = ts,
-                "wakes up" => naps.push(Nap {
-                    id: current_guard,
-                    start: nap_start,
-                    end: ts,
-                }),
-                st => {
-                    let chunks: Vec<_> = st.split(" ").collect();
-                    current_guard = chunks[1].trim_matches('#').parse().unwrap();
+        let mut shifts = vec![];
+        //Guard on duty and the night of their shift
+        let mut current: Option<(u16, NaiveDate)> = None;
+        let mut asleep: Option<&Record> = None;
+        for record in records.iter() {
+            match record.event {
+                Event::BeginsShift(id) => {
+                    if let Some(sleep) = asleep {
+                        return Err(error(sleep, |line, text| ScheduleError::SleepNeverEnded {
+                            line,
+                            text,
+                        }));
+                    }
+                    let date = shift_date(record.time).ok_or_else(|| {
+                        error(record, |line, text| ScheduleError::Malformed { line, text })
+                    })?;
+                    current = Some((id, date));
+                    shifts.push((date, id));
+                }
+                Event::FallsAsleep => {
+                    if current.is_none() {
+                        return Err(error(record, |line, text| ScheduleError::UnknownGuard {
+                            line,
+                            text,
+                        }));
+                    }
+                    if asleep.is_some() {
+                        return Err(error(record, |line, text| ScheduleError::DoubleSleep {
+                            line,
+                            text,
+                        }));
+                    }
+                    asleep = Some(record);
+                }
+                Event::WakesUp => {
+                    let (id, date) = current.ok_or_else(|| {
+                        error(record, |line, text| ScheduleError::UnknownGuard {
+                            line,
+                            text,
+                        })
+                    })?;
+                    let sleep = asleep.take().ok_or_else(|| {
+                        error(record, |line, text| ScheduleError::WakeWithoutSleep {
+                            line,
+                            text,
+                        })
+                    })?;
+                    naps.push(Nap {
+                        id,
+                        date,
+                        start: sleep.time,
+                        end: record.time,
+                    })
                 }
             }
-        });
-        return Ok(Self { naps });
+        }
+        if let Some(sleep) = asleep {
+            return Err(error(sleep, |line, text| ScheduleError::SleepNeverEnded {
+                line,
+                text,
+            }));
+        }
+        Ok(Self { naps, shifts })
     }
 }
 
 impl Schedule {
-    fn most_consisten_sleeper(&self) -> (u16, u8) {
-        let mut sleep_minutes: HashMap<(u16, u8), u8> = HashMap::new();
+    //None if nobody ever slept
+    fn most_consisten_sleeper(&self) -> Option<(u16, u8)> {
+        let (id, minute, _) = self.top_minutes(1).into_iter().next()?;
+        Some((id, minute))
+    }
+
+    //None if nobody ever slept
+    fn best_sleeper(&self) -> Option<(u16, u8)> {
+        let (id, _) = self
+            .top_sleepers(1)
+            .into_iter()
+            .find(|(_, total)| *total > 0)?;
+        let histogram = self.histograms()[&id];
+        let minute = (0..60).max_by_key(|m| (histogram[*m], Reverse(*m)))?;
+        Some((id, minute as u8))
+    }
+
+    //How many nights every guard was asleep on each minute of the midnight hour
+    fn histograms(&self) -> BTreeMap<u16, [usize; 60]> {
+        let mut histograms = BTreeMap::new();
         for nap in &self.naps {
+            let histogram = histograms.entry(nap.id).or_insert([0; 60]);
             for minute in nap.minutes_of_sleep() {
-                let entry = sleep_minutes.entry((nap.id, minute)).or_insert(0);
-                *entry += 1;
+                histogram[minute as usize] += 1;
             }
         }
-        *sleep_minutes
-            .iter()
-            .max_by(|(_, c1), (_, c2)| c1.cmp(c2))
-            .ok_or("Can't find max")
-            .unwrap()
-            .0
+        histograms
     }
 
-    fn best_sleeper(&self) -> (u16, u8) {
-        let mut sleep_minutes: HashMap<u16, Vec<u8>> = HashMap::new();
-        for nap in &self.naps {
-            for minute in nap.minutes_of_sleep() {
-                let entry = sleep_minutes.entry(nap.id).or_insert(vec![]);
-                (*entry).push(minute);
+    //Guards with the most minutes asleep in total, ties go to the lower id
+    fn top_sleepers(&self, n: usize) -> Vec<(u16, usize)> {
+        let mut totals = self
+            .histograms()
+            .into_iter()
+            .map(|(id, histogram)| (id, histogram.iter().sum()))
+            .collect::<Vec<_>>();
+        totals.sort_by_key(|(id, total)| (Reverse(*total), *id));
+        totals.truncate(n);
+        totals
+    }
+
+    //(guard, minute) pairs seen asleep on the most nights, with the number of nights
+    fn top_minutes(&self, n: usize) -> Vec<(u16, u8, usize)> {
+        let mut pairs = self
+            .histograms()
+            .into_iter()
+            .flat_map(|(id, histogram)| {
+                (0..60).map(move |minute| (id, minute as u8, histogram[minute]))
+            })
+            .filter(|(_, _, nights)| *nights > 0)
+            .collect::<Vec<_>>();
+        pairs.sort_by_key(|(id, minute, nights)| (Reverse(*nights), *id, *minute));
+        pairs.truncate(n);
+        pairs
+    }
+
+    //Minutes asleep during every shift, in chronological order
+    fn nights(&self) -> Vec<(NaiveDate, u16, usize)> {
+        let mut shifts = self.shifts.clone();
+        shifts.dedup();
+        shifts
+            .into_iter()
+            .map(|(date, id)| {
+                let minutes = self
+                    .naps
+                    .iter()
+                    .filter(|nap| nap.date == date && nap.id == id)
+                    .map(|nap| nap.minutes_of_sleep().len())
+                    .sum();
+                (date, id, minutes)
+            })
+            .collect()
+    }
+
+    //Puzzle-style table with a row per night and guard, # for asleep and . for awake.
+    //Only the given guard's shifts if there is one
+    fn chart(&self, guard: Option<u16>) -> String {
+        let mut rows = self.shifts.clone();
+        rows.dedup();
+        rows.retain(|(_, id)| guard.is_none_or(|guard| guard == *id));
+        let id_width = rows
+            .iter()
+            .map(|(_, id)| id.to_string().len() + 1)
+            .max()
+            .unwrap_or(0)
+            .max(3);
+        let indent = " ".repeat(7 + id_width + 2);
+        let mut result = format!("{:7}{:width$}Minute\n", "Date", "ID", width = id_width + 2);
+        result += &indent;
+        result += &(0..60).map(|m| (b'0' + m / 10) as char).collect::<String>();
+        result += "\n";
+        result += &indent;
+        result += &(0..60).map(|m| (b'0' + m % 10) as char).collect::<String>();
+        result += "\n";
+        for (date, id) in rows {
+            let mut minutes = vec!['.'; 60];
+            for nap in self
+                .naps
+                .iter()
+                .filter(|nap| nap.date == date && nap.id == id)
+            {
+                for minute in nap.minutes_of_sleep() {
+                    minutes[minute as usize] = '#';
+                }
             }
+            result += &format!(
+                "{}  {:width$}{}\n",
+                date.format("%m-%d"),
+                format!("#{}", id),
+                minutes.into_iter().collect::<String>(),
+                width = id_width + 2
+            );
         }
-        let res = sleep_minutes
-            .iter()
-            .max_by(|(_, xminutes), (_, yminutes)| xminutes.len().cmp(&yminutes.len()))
-            .ok_or("Can't get max")
-            .unwrap()
-            .clone();
-        let best_sleepers_minutes =
-            res.1
+        result
+    }
+}
+
+enum Cell {
+    Number(usize),
+    Text(String),
+}
+
+//Rows of a report ready to be exported
+struct Table {
+    columns: Vec<&'static str>,
+    rows: Vec<Vec<Cell>>,
+}
+
+impl Table {
+    fn to_csv(&self) -> String {
+        let mut result = self.columns.join(",") + "\n";
+        for row in &self.rows {
+            let cells = row
                 .iter()
-                .fold(HashMap::new(), |mut acc: HashMap<u8, u16>, minute| {
-                    {
-                        let entry = acc.entry(*minute).or_insert(0);
-                        *entry += 1;
+                .map(|cell| match cell {
+                    Cell::Number(n) => n.to_string(),
+                    Cell::Text(text) if text.contains([',', '"']) => {
+                        format!("\"{}\"", text.replace('"', "\"\""))
                     }
-                    acc
-                });
-        let best_minute = best_sleepers_minutes
+                    Cell::Text(text) => text.clone(),
+                })
+                .collect::<Vec<_>>();
+            result += &cells.join(",");
+            result += "\n";
+        }
+        result
+    }
+
+    //Array with an object per row
+    fn to_json(&self) -> String {
+        let rows = self
+            .rows
             .iter()
-            .max_by(|x, y| x.1.cmp(&y.1))
-            .ok_or("Can't get max")
-            .unwrap()
-            .0;
-        return (*res.0, *best_minute);
+            .map(|row| {
+                let fields = self
+                    .columns
+                    .iter()
+                    .zip(row.iter())
+                    .map(|(column, cell)| match cell {
+                        Cell::Number(n) => format!("\"{}\":{}", column, n),
+                        Cell::Text(text) => format!(
+                            "\"{}\":\"{}\"",
+                            column,
+                            text.replace('\\', "\\\\").replace('"', "\\\"")
+                        ),
+                    })
+                    .collect::<Vec<_>>();
+                format!("{{{}}}", fields.join(","))
+            })
+            .collect::<Vec<_>>();
+        format!("[{}]", rows.join(","))
     }
 }
 
+//Table for one of the reports: sleepers, minutes, histograms or nights.
+//Rankings are cut to the top `n` rows
+fn report(sched: &Schedule, kind: &str, n: usize) -> Option<Table> {
+    let table = match kind {
+        "sleepers" => Table {
+            columns: vec!["guard", "minutes"],
+            rows: sched
+                .top_sleepers(n)
+                .into_iter()
+                .map(|(id, total)| vec![Cell::Number(id as usize), Cell::Number(total)])
+                .collect(),
+        },
+        "minutes" => Table {
+            columns: vec!["guard", "minute", "nights"],
+            rows: sched
+                .top_minutes(n)
+                .into_iter()
+                .map(|(id, minute, nights)| {
+                    vec![
+                        Cell::Number(id as usize),
+                        Cell::Number(minute as usize),
+                        Cell::Number(nights),
+                    ]
+                })
+                .collect(),
+        },
+        "histograms" => Table {
+            columns: vec!["guard", "minute", "nights"],
+            rows: sched
+                .histograms()
+                .into_iter()
+                .flat_map(|(id, histogram)| {
+                    (0..60).map(move |minute| {
+                        vec![
+                            Cell::Number(id as usize),
+                            Cell::Number(minute),
+                            Cell::Number(histogram[minute]),
+                        ]
+                    })
+                })
+                .collect(),
+        },
+        "nights" => Table {
+            columns: vec!["date", "guard", "minutes"],
+            rows: sched
+                .nights()
+                .into_iter()
+                .map(|(date, id, minutes)| {
+                    vec![
+                        Cell::Text(date.format("%Y-%m-%d").to_string()),
+                        Cell::Number(id as usize),
+                        Cell::Number(minutes),
+                    ]
+                })
+                .collect(),
+        },
+        _ => return None,
+    };
+    Some(table)
+}
+
 #[derive(Debug)]
 struct Nap {
     id: u16,
+    //Night of the shift, the date of the midnight hour
+    date: NaiveDate,
     start: NaiveDateTime,
     end: NaiveDateTime,
 }
 
 impl Nap {
+    //Minutes of the midnight hour the guard spent asleep, the only ones that matter
     fn minutes_of_sleep(&self) -> Vec<u8> {
-        let mut current = self.start.clone();
-        let mut result = vec![];
-        while current < self.end {
-            result.push(current.time().format("%M").to_string().parse().unwrap());
-            current = current + Duration::minutes(1)
-        }
-        result
+        let midnight = match self.date.and_hms_opt(0, 0, 0) {
+            Some(midnight) => midnight,
+            None => return vec![],
+        };
+        let from = (self.start - midnight).num_minutes().max(0);
+        let to = (self.end - midnight).num_minutes().min(60);
+        (from..to).map(|minute| minute as u8).collect()
     }
 }
 
 fn main() {
     let mut input = String::new();
     io::stdin().read_to_string(&mut input).unwrap();
-    let sched: Schedule = input.parse().unwrap();
-    let part1_res = sched.best_sleeper();
-    println!(
-        "Part1 {:?} =>  {}",
-        part1_res,
-        part1_res.0 as u32 * part1_res.1 as u32
+    let sched: Schedule = match input.parse() {
+        Ok(sched) => sched,
+        Err(e) => {
+            eprintln!("{}", e);
+            process::exit(1);
+        }
+    };
+    let mut chart = false;
+    let mut guard = None;
+    let mut report_kind = None;
+    let mut top = 10;
+    let mut json = false;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_ref() {
+            "--chart" => chart = true,
+            "--guard" => {
+                let id = args.next().expect("Guard id expected");
+                guard = Some(
+                    id.trim_matches('#')
+                        .parse()
+                        .expect("Guard id should be a number"),
+                );
+            }
+            "--report" => report_kind = Some(args.next().expect("Report kind expected")),
+            "--top" => {
+                top = args
+                    .next()
+                    .and_then(|n| n.parse().ok())
+                    .expect("--top needs a number")
+            }
+            "--json" => json = true,
+            _ => panic!("Unknown argument {}", arg),
+        }
+    }
+    if let Some(kind) = report_kind {
+        let table = report(&sched, &kind, top).unwrap_or_else(|| {
+            panic!(
+                "Unknown report {}, use sleepers, minutes, histograms or nights",
+                kind
+            )
+        });
+        if json {
+            println!("{}", table.to_json());
+        } else {
+            print!("{}", table.to_csv());
+        }
+        return;
+    }
+    if chart || guard.is_some() {
+        print!("{}", sched.chart(guard));
+        return;
+    }
+    match (sched.best_sleeper(), sched.most_consisten_sleeper()) {
+        (Some(part1_res), Some(part2_res)) => {
+            println!(
+                "Part1 {:?} =>  {}",
+                part1_res,
+                part1_res.0 as u32 * part1_res.1 as u32
+            );
+            println!(
+                "Part2 {:?} =>  {}",
+                part2_res,
+                part2_res.0 as u32 * part2_res.1 as u32
+            );
+        }
+        _ => println!("Nobody slept on duty"),
+    }
+}
+
+#[cfg(test)]
+const EXAMPLE: &str = "[1518-11-01 00:00] Guard #10 begins shift
+[1518-11-01 00:05] falls asleep
+[1518-11-01 00:25] wakes up
+[1518-11-01 00:30] falls asleep
+[1518-11-01 00:55] wakes up
+[1518-11-01 23:58] Guard #99 begins shift
+[1518-11-02 00:40] falls asleep
+[1518-11-02 00:50] wakes up
+[1518-11-03 00:05] Guard #10 begins shift
+[1518-11-03 00:24] falls asleep
+[1518-11-03 00:29] wakes up
+[1518-11-04 00:02] Guard #99 begins shift
+[1518-11-04 00:36] falls asleep
+[1518-11-04 00:46] wakes up
+[1518-11-05 00:03] Guard #99 begins shift
+[1518-11-05 00:45] falls asleep
+[1518-11-05 00:55] wakes up
+";
+
+#[test]
+fn test_schedule() {
+    //Order of the records doesn't matter
+    let mut lines = EXAMPLE.lines().collect::<Vec<_>>();
+    lines.reverse();
+    let sched: Schedule = lines.join("\n").parse().unwrap();
+    assert_eq!(sched.naps.len(), 6);
+    assert_eq!(sched.naps[2].id, 99);
+    assert_eq!(
+        sched.naps[2].date,
+        NaiveDate::from_ymd_opt(1518, 11, 2).unwrap()
+    );
+    assert_eq!(sched.best_sleeper(), Some((10, 24)));
+    assert_eq!(sched.most_consisten_sleeper(), Some((99, 45)));
+}
+
+#[test]
+fn test_schedule_errors() {
+    let error = |input: &str| input.parse::<Schedule>().err().unwrap().to_string();
+    assert_eq!(
+        error("[1518-11-01 00:00] Guard #10 begins shift\n[1518-11-01 00:25] wakes up"),
+        "Line 2: guard wakes up without falling asleep \"[1518-11-01 00:25] wakes up\""
+    );
+    assert_eq!(
+        error("[1518-11-01 00:05] falls asleep\n[1518-11-01 00:00] Guard #10 begins shift\n[1518-11-01 00:06] falls asleep"),
+        "Line 3: guard falls asleep while already asleep \"[1518-11-01 00:06] falls asleep\""
+    );
+    assert_eq!(
+        error("[1518-11-01 00:00] Guard #10 begins shift\n[1518-11-01 00:05] falls asleep\n[1518-11-01 23:58] Guard #99 begins shift"),
+        "Line 2: guard falls asleep and never wakes up \"[1518-11-01 00:05] falls asleep\""
     );
-    let part2_res = sched.most_consisten_sleeper();
-    println!(
-        "Part2 {:?} =>  {}",
-        part2_res,
-        part2_res.0 as u32 * part2_res.1 as u32
+    assert_eq!(
+        error("[1518-11-01 00:05] falls asleep"),
+        "Line 1: no guard is on duty \"[1518-11-01 00:05] falls asleep\""
     );
+    assert_eq!(
+        error("[1518-11-01 00:00] Guard 10 begins shift"),
+        "Line 1: malformed record \"[1518-11-01 00:00] Guard 10 begins shift\""
+    );
+}
+
+#[test]
+fn test_chart() {
+    let sched: Schedule = EXAMPLE.parse().unwrap();
+    assert_eq!(
+        sched.chart(None),
+        "Date   ID   Minute
+            000000000011111111112222222222333333333344444444445555555555
+            012345678901234567890123456789012345678901234567890123456789
+11-01  #10  .....####################.....#########################.....
+11-02  #99  ........................................##########..........
+11-03  #10  ........................#####...............................
+11-04  #99  ....................................##########..............
+11-05  #99  .............................................##########.....
+"
+    );
+    assert_eq!(
+        sched.chart(Some(10)).lines().skip(3).collect::<Vec<_>>(),
+        vec![
+            "11-01  #10  .....####################.....#########################.....",
+            "11-03  #10  ........................#####...............................",
+        ]
+    );
+}
+
+#[test]
+fn test_analytics() {
+    let sched: Schedule = EXAMPLE.parse().unwrap();
+    assert_eq!(sched.top_sleepers(5), vec![(10, 50), (99, 30)]);
+    assert_eq!(sched.top_minutes(2), vec![(99, 45, 3), (10, 24, 2)]);
+    let histograms = sched.histograms();
+    assert_eq!(histograms[&10][24], 2);
+    assert_eq!(histograms[&99][..40].iter().sum::<usize>(), 4);
+    assert_eq!(
+        sched.nights()[1],
+        (NaiveDate::from_ymd_opt(1518, 11, 2).unwrap(), 99, 10)
+    );
+
+    let table = report(&sched, "sleepers", 1).unwrap();
+    assert_eq!(table.to_csv(), "guard,minutes\n10,50\n");
+    assert_eq!(table.to_json(), "[{\"guard\":10,\"minutes\":50}]");
+    let table = report(&sched, "nights", 10).unwrap();
+    assert_eq!(table.to_csv().lines().nth(3), Some("1518-11-03,10,5"));
+    assert!(report(&sched, "guards", 1).is_none());
+
+    //Guards that never fall asleep, or no guards at all
+    for input in &["[1518-11-01 00:00] Guard #10 begins shift", ""] {
+        let sched: Schedule = input.parse().unwrap();
+        assert_eq!(sched.best_sleeper(), None);
+        assert_eq!(sched.most_consisten_sleeper(), None);
+    }
 }
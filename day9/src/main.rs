@@ -1,95 +1,437 @@
 use std::collections::HashMap;
+use std::env;
 use std::io::{self, Read};
+use std::str::FromStr;
+
+#[derive(Clone, Debug)]
+struct Rules {
+    //Marbles divisible by this are kept instead of being placed
+    modulus: u32,
+    //Marble removed on such a turn, relative to the current one
+    removal_offset: i32,
+    //Where a newly placed marble ends up, relative to the current one
+    insertion_offset: i32,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Self {
+            modulus: 23,
+            removal_offset: -7,
+            insertion_offset: 2,
+        }
+    }
+}
+
+struct Options {
+    rules: Rules,
+    //Print the circle for these turns (inclusive) of the first game
+    trace: Option<(u32, u32)>,
+    //Input is a list of games, one per line
+    batch: bool,
+}
+
+impl Options {
+    fn from_args() -> Self {
+        let mut rules = Rules::default();
+        let mut trace = None;
+        let mut batch = false;
+        let mut args = env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--trace" => {
+                    trace = trace.or(Some((0, u32::MAX)));
+                    continue;
+                }
+                "--batch" => {
+                    batch = true;
+                    continue;
+                }
+                _ => (),
+            }
+            let value = args
+                .next()
+                .unwrap_or_else(|| panic!("{} requires a value", arg));
+            match arg.as_str() {
+                "--modulus" => rules.modulus = value.parse().unwrap(),
+                "--removal-offset" => rules.removal_offset = value.parse().unwrap(),
+                "--insertion-offset" => rules.insertion_offset = value.parse().unwrap(),
+                "--turns" => {
+                    let range = value
+                        .split('-')
+                        .map(|turn| turn.parse().unwrap())
+                        .collect::<Vec<_>>();
+                    if range.len() != 2 {
+                        panic!("--turns expects a range like 10-25, got {}", value)
+                    }
+                    trace = Some((range[0], range[1]))
+                }
+                _ => panic!("Unknown argument {}", arg),
+            }
+        }
+        //Otherwise we could remove the last marble of the circle
+        if rules.modulus < 2 {
+            panic!("Modulus should be at least 2")
+        }
+        Self {
+            rules,
+            trace,
+            batch,
+        }
+    }
+}
+
+//"10 players; last marble is worth 1618 points", optionally followed by
+//": high score is 8317" like in the examples
+struct Setup {
+    players: u32,
+    stones: u32,
+    expected: Option<u64>,
+}
+
+impl FromStr for Setup {
+    type Err = String;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let mut parts = line.splitn(2, ':');
+        let words = parts
+            .next()
+            .unwrap_or("")
+            .split_whitespace()
+            .collect::<Vec<_>>();
+        if words.len() != 8 || words[1..6] != ["players;", "last", "marble", "is", "worth"] {
+            return Err(format!("Unexpected game description: {}", line));
+        }
+        let players = words[0]
+            .parse()
+            .map_err(|_| format!("Invalid number of players: {}", words[0]))?;
+        if players == 0 {
+            return Err("There should be at least one player".to_string());
+        }
+        let stones = words[6]
+            .parse()
+            .map_err(|_| format!("Invalid last marble: {}", words[6]))?;
+        let expected = match parts.next() {
+            None => None,
+            Some(rest) => {
+                let words = rest.split_whitespace().collect::<Vec<_>>();
+                if words.len() != 4 || words[..3] != ["high", "score", "is"] {
+                    return Err(format!("Unexpected high score: {}", rest.trim()));
+                }
+                Some(
+                    words[3]
+                        .parse()
+                        .map_err(|_| format!("Invalid high score: {}", words[3]))?,
+                )
+            }
+        };
+        Ok(Self {
+            players,
+            stones,
+            expected,
+        })
+    }
+}
 
 struct Game {
     stones: u32,
     players: u32,
-    scores: HashMap<u32, u32>,
-    circle: Vec<u32>,
-    current: usize,
+    rules: Rules,
+    scores: HashMap<u32, u64>,
+    //The circle as a ring of links indexed by marble number:
+    //next[m] is clockwise of marble m, prev[m] is counterclockwise of it
+    next: Vec<u32>,
+    prev: Vec<u32>,
+    current: u32,
+    //Marble the circle is printed from. It's 0 unless that one gets removed
+    first: u32,
+    trace: Option<(u32, u32)>,
 }
 
 impl Game {
-    fn new(stones: u32, players: u32) -> Self {
-        //It's a bit too much but it's simple and helps to avoid reallocations
-        let mut circle = Vec::with_capacity(stones as usize);
-        circle.push(0);
+    fn new(stones: u32, players: u32, rules: Rules) -> Self {
+        //Every marble gets its slot right away, so we never reallocate
+        let size = stones as usize + 1;
         Self {
             scores: HashMap::new(),
+            next: vec![0; size],
+            prev: vec![0; size],
             current: 0,
-            circle,
+            first: 0,
+            trace: None,
             stones,
             players,
+            rules,
         }
     }
 
-    fn position_at(&self, offset: i32) -> usize {
+    fn marble_at(&self, offset: i32) -> u32 {
         //Positive numbers mean clocwise offset. Negative -- counterclockwise
-        let mut index = self.current as i32 + offset;
-        if index < 0 {
-            index += self.circle.len() as i32
+        let mut marble = self.current;
+        for _ in 0..offset.abs() {
+            marble = match offset > 0 {
+                true => self.next[marble as usize],
+                false => self.prev[marble as usize],
+            }
         }
-        (index as usize) % self.circle.len()
+        marble
+    }
+
+    //Marble that the new one is placed after
+    fn insert_position(&self) -> u32 {
+        self.marble_at(self.rules.insertion_offset - 1)
     }
 
-    fn insert_position(&self) -> usize {
-        self.position_at(2)
+    fn insert_after(&mut self, marble: u32, stone: u32) {
+        let next = self.next[marble as usize];
+        self.next[marble as usize] = stone;
+        self.prev[next as usize] = stone;
+        self.next[stone as usize] = next;
+        self.prev[stone as usize] = marble;
+    }
+
+    fn remove(&mut self, marble: u32) {
+        let (prev, next) = (self.prev[marble as usize], self.next[marble as usize]);
+        self.next[prev as usize] = next;
+        self.prev[next as usize] = prev;
+        if marble == self.first {
+            self.first = next
+        }
     }
 
     fn step(&mut self, stone: u32, player: u32) {
-        match stone % 23 {
+        match stone % self.rules.modulus {
             0 => {
-                let extract_index = self.position_at(-7);
-                let score_inc = stone + self.circle[extract_index];
+                let extracted = self.marble_at(self.rules.removal_offset);
+                let score_inc = (stone + extracted) as u64;
                 {
                     let score = self.scores.entry(player).or_insert(0);
                     *score += score_inc
                 }
-                self.current = extract_index;
-                self.circle.remove(extract_index);
-                //Just in case we removed the last elemen of the circle
-                self.current = self.position_at(0);
+                self.current = self.next[extracted as usize];
+                self.remove(extracted);
             }
             _ => {
-                let insert_index = self.insert_position();
-                self.circle.insert(insert_index, stone);
-                self.current = insert_index;
+                let insert_after = self.insert_position();
+                self.insert_after(insert_after, stone);
+                self.current = stone;
             }
         }
     }
 
+    fn score(&self, player: u32) -> u64 {
+        *self.scores.get(&player).unwrap_or(&0)
+    }
+
+    //Best player and their score. First one wins the tie
+    fn winner(&self) -> (u32, u64) {
+        (0..self.players)
+            .map(|p| (p, self.score(p)))
+            .fold((0, 0), |best, (p, score)| match score > best.1 {
+                true => (p, score),
+                false => best,
+            })
+    }
+
+    //The circle the way the puzzle shows it: `[5]  0 16  8 17  4 18(19) 2 20`
+    fn trace_line(&self, player: Option<u32>) -> String {
+        let width = self.stones.to_string().len() + 1;
+        let mut line = match player {
+            None => "[-]".to_string(),
+            Some(p) => format!("[{}]", p + 1),
+        };
+        let mut marble = self.first;
+        let mut closing = false;
+        loop {
+            let cell = match marble == self.current {
+                true => format!("{:>width$}", format!("({}", marble), width = width),
+                false => format!("{:>width$}", marble, width = width),
+            };
+            //Closing parenthesis takes place of the padding of the next marble
+            if closing {
+                line.push(')');
+                line.push_str(cell.strip_prefix(' ').unwrap_or(&cell));
+            } else {
+                line.push_str(&cell);
+            }
+            closing = marble == self.current;
+            marble = self.next[marble as usize];
+            if marble == self.first {
+                break;
+            }
+        }
+        if closing {
+            line.push(')');
+        }
+        line
+    }
+
+    fn traced(&self, turn: u32) -> bool {
+        match self.trace {
+            None => false,
+            Some((from, to)) => from <= turn && turn <= to,
+        }
+    }
+
     fn play(&mut self) {
+        if self.traced(0) {
+            println!("{}", self.trace_line(None));
+        }
         let mut player = 0;
         for i in 1..=self.stones {
             self.step(i, player);
-            if i % 10000 == 0 {
-                println!("{}: [{}] {:?}", i, player, self.circle.len());
+            if self.traced(i) {
+                println!("{}", self.trace_line(Some(player)));
             }
             player = (player + 1) % self.players;
         }
     }
 }
 
+fn batch(input: &str, options: &Options) {
+    let mut mismatches = 0;
+    for (i, line) in input.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let setup: Setup = line
+            .parse()
+            .unwrap_or_else(|e| panic!("Line {}: {}", i + 1, e));
+        let mut game = Game::new(setup.stones, setup.players, options.rules.clone());
+        game.trace = options.trace;
+        game.play();
+
+        let (winner, top) = game.winner();
+        println!("{}", line.trim());
+        println!("  Winner: player {} with {} points", winner + 1, top);
+        println!(
+            "  Scores: {}",
+            (0..game.players)
+                .map(|p| format!("{}: {}", p + 1, game.score(p)))
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        match setup.expected {
+            None => (),
+            Some(expected) if expected == top => println!("  Expected {}: OK", expected),
+            Some(expected) => {
+                mismatches += 1;
+                println!("  Expected {}: MISMATCH", expected)
+            }
+        }
+    }
+    if mismatches > 0 {
+        println!("{} games didn't match the expected score", mismatches);
+    }
+}
+
 fn main() {
     let mut input = String::new();
     io::stdin().read_to_string(&mut input).unwrap();
-    let chunks = input.split(" ").collect::<Vec<_>>();
-    let mut game = Game::new(chunks[6].parse().unwrap(), chunks[0].parse().unwrap());
+    let options = Options::from_args();
+    if options.batch {
+        batch(&input, &options);
+        return;
+    }
+
+    let setup: Setup = input.trim().parse().unwrap();
+    let mut game = Game::new(setup.stones, setup.players, options.rules.clone());
+    game.trace = options.trace;
     game.play();
+    println!("Top score is {}", game.winner().1);
 
-    println!(
-        "Top score is {}",
-        game.scores.values().max().ok_or("No max score?").unwrap()
-    );
+    let mut game = Game::new(setup.stones * 100, setup.players, options.rules);
+    game.play();
+    println!("Top score is {}", game.winner().1);
+}
 
-    let mut game = Game::new(
-        chunks[6].parse::<u32>().unwrap() * 100,
-        chunks[0].parse().unwrap(),
-    );
+#[test]
+fn test_winner() {
+    for line in include_str!("../games").lines() {
+        let setup: Setup = line.parse().unwrap();
+        let mut game = Game::new(setup.stones, setup.players, Rules::default());
+        game.play();
+        assert_eq!(Some(game.winner().1), setup.expected, "{}", line);
+    }
+}
 
-    game.play();
-    println!(
-        "Top score is {}",
-        game.scores.values().max().ok_or("No max score?").unwrap()
-    );
+#[test]
+fn test_trace() {
+    //The example circle from the README, turns 0 to 25
+    let expected = "\
+[-] (0)
+[1]  0 (1)
+[2]  0 (2) 1
+[3]  0  2  1 (3)
+[4]  0 (4) 2  1  3
+[5]  0  4  2 (5) 1  3
+[6]  0  4  2  5  1 (6) 3
+[7]  0  4  2  5  1  6  3 (7)
+[8]  0 (8) 4  2  5  1  6  3  7
+[9]  0  8  4 (9) 2  5  1  6  3  7
+[1]  0  8  4  9  2(10) 5  1  6  3  7
+[2]  0  8  4  9  2 10  5(11) 1  6  3  7
+[3]  0  8  4  9  2 10  5 11  1(12) 6  3  7
+[4]  0  8  4  9  2 10  5 11  1 12  6(13) 3  7
+[5]  0  8  4  9  2 10  5 11  1 12  6 13  3(14) 7
+[6]  0  8  4  9  2 10  5 11  1 12  6 13  3 14  7(15)
+[7]  0(16) 8  4  9  2 10  5 11  1 12  6 13  3 14  7 15
+[8]  0 16  8(17) 4  9  2 10  5 11  1 12  6 13  3 14  7 15
+[9]  0 16  8 17  4(18) 9  2 10  5 11  1 12  6 13  3 14  7 15
+[1]  0 16  8 17  4 18  9(19) 2 10  5 11  1 12  6 13  3 14  7 15
+[2]  0 16  8 17  4 18  9 19  2(20)10  5 11  1 12  6 13  3 14  7 15
+[3]  0 16  8 17  4 18  9 19  2 20 10(21) 5 11  1 12  6 13  3 14  7 15
+[4]  0 16  8 17  4 18  9 19  2 20 10 21  5(22)11  1 12  6 13  3 14  7 15
+[5]  0 16  8 17  4 18(19) 2 20 10 21  5 22 11  1 12  6 13  3 14  7 15
+[6]  0 16  8 17  4 18 19  2(24)20 10 21  5 22 11  1 12  6 13  3 14  7 15
+[7]  0 16  8 17  4 18 19  2 24 20(25)10 21  5 22 11  1 12  6 13  3 14  7 15";
+    let mut game = Game::new(25, 9, Rules::default());
+    let mut lines = vec![game.trace_line(None)];
+    for i in 1..=25 {
+        let player = (i - 1) % 9;
+        game.step(i, player);
+        lines.push(game.trace_line(Some(player)));
+    }
+    assert_eq!(lines, expected.lines().collect::<Vec<_>>());
+}
+
+#[test]
+fn test_setup_errors() {
+    let cases = [
+        (
+            "10 players; first marble is worth 1618 points",
+            "Unexpected game description: 10 players; first marble is worth 1618 points",
+        ),
+        (
+            "10 players; last marble is worth 1618",
+            "Unexpected game description: 10 players; last marble is worth 1618",
+        ),
+        (
+            "ten players; last marble is worth 1618 points",
+            "Invalid number of players: ten",
+        ),
+        (
+            "0 players; last marble is worth 1618 points",
+            "There should be at least one player",
+        ),
+        (
+            "10 players; last marble is worth many points",
+            "Invalid last marble: many",
+        ),
+        (
+            "10 players; last marble is worth 1618 points: score is 8317",
+            "Unexpected high score: score is 8317",
+        ),
+        (
+            "10 players; last marble is worth 1618 points: high score is",
+            "Unexpected high score: high score is",
+        ),
+        (
+            "10 players; last marble is worth 1618 points: high score is big",
+            "Invalid high score: big",
+        ),
+    ];
+    for (line, error) in cases.iter() {
+        assert_eq!(line.parse::<Setup>().err().as_deref(), Some(*error));
+    }
 }